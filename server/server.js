@@ -254,9 +254,11 @@ function handleAdminMessage(message) {
 }
 
 app.post('/upload', upload.any(), (req, res) => {
+    const frameCount = parseInt(req.body.frameCount, 10);
+
     if (!req.files || req.files.length === 0) {
         return res.status(400).send('No files were uploaded.');
-    } else if (req.files.length !== 8) {
+    } else if (!Number.isInteger(frameCount) || req.files.length !== frameCount) {
         return res.status(400).send('Invalid amount of frames sent');
     }
 
@@ -1,6 +1,7 @@
 use crate::app::PaintingApp;
-use eframe::egui::{self, Color32, Stroke, Pos2, Rect};
-use crate::models::{Stroke as DrawingStroke, StrokeType};
+use eframe::egui::{self, Color32, Stroke, Pos2, Rect, Mesh, Shape};
+use crate::models::{Stroke as DrawingStroke, StrokeType, in_paint_order};
+use crate::utils::{canvas_to_screen, taper_segment};
 
 impl PaintingApp {
     pub fn update_animation(&mut self, ctx: &egui::Context) {
@@ -16,7 +17,11 @@ impl PaintingApp {
         }
     }
 
-    pub fn draw_onion_skins(&self, painter: &egui::Painter) {
+    pub fn draw_onion_skins(&self, painter: &egui::Painter, canvas_rect: Rect) {
+        if self.frames.len() < 2 {
+            return;
+        }
+
         let prev_frame_index = if self.current_frame > 0 {
             self.current_frame - 1
         } else {
@@ -30,20 +35,22 @@ impl PaintingApp {
         };
 
         let prev_frame = &self.frames[prev_frame_index];
-        for stroke in prev_frame {
+        for stroke in in_paint_order(prev_frame) {
             self.draw_onion_skin_stroke(
                 painter,
                 stroke,
+                canvas_rect,
                 self.prev_onion_color,
                 self.onion_skin_opacity,
             );
         }
 
         let next_frame = &self.frames[next_frame_index];
-        for stroke in next_frame {
+        for stroke in in_paint_order(next_frame) {
             self.draw_onion_skin_stroke(
                 painter,
                 stroke,
+                canvas_rect,
                 self.next_onion_color,
                 self.onion_skin_opacity,
             );
@@ -54,6 +61,7 @@ impl PaintingApp {
         &self,
         painter: &egui::Painter,
         stroke: &DrawingStroke,
+        canvas_rect: Rect,
         color: Color32,
         opacity: f32,
     ) {
@@ -68,28 +76,56 @@ impl PaintingApp {
             (opacity * 255.0) as u8,
         );
 
+        let to_screen = canvas_to_screen(canvas_rect);
+        let scale = canvas_rect.width();
+
+        if stroke.stroke_type == StrokeType::Fill {
+            // keep fills fainter so the outlines on top stay readable
+            let fill_color = onion_color.gamma_multiply(0.5);
+            painter.add(fill_shape(stroke.fill_rects().map(|r| to_screen.transform_rect(r)), fill_color));
+            return;
+        }
+
+        if stroke.is_tapered() {
+            let points: Vec<Pos2> = stroke.points.iter().map(|p| to_screen * *p).collect();
+            let widths: Vec<f32> = stroke.widths.iter().map(|w| w * scale).collect();
+            draw_tapered(painter, &points, &widths, onion_color);
+            return;
+        }
+
         for window in stroke.points.windows(2) {
-            let p1 = window[0];
-            let p2 = window[1];
+            let p1 = to_screen * window[0];
+            let p2 = to_screen * window[1];
 
-            painter.line_segment([p1, p2], Stroke::new(stroke.size, onion_color));
+            painter.line_segment([p1, p2], Stroke::new(stroke.size * scale, onion_color));
         }
     }
 
-    pub fn draw_stroke(&self, painter: &egui::Painter, stroke: &DrawingStroke) {
+    // `canvas_rect` is the zoomed canvas, sizes are fractions of its width so they zoom with it
+    pub fn draw_stroke(&self, painter: &egui::Painter, stroke: &DrawingStroke, canvas_rect: Rect) {
+        let to_screen = canvas_to_screen(canvas_rect);
+        let scale = canvas_rect.width();
+
         match stroke.stroke_type {
             StrokeType::Draw => {
                 if stroke.points.len() == 1 {
-                    let point = stroke.points[0];
-                    painter.circle_filled(point, stroke.size / 2.0, stroke.color);
+                    let point = to_screen * stroke.points[0];
+                    painter.circle_filled(point, stroke.width_at(0) * scale / 2.0, stroke.color);
+                } else if stroke.is_tapered() {
+                    let points: Vec<Pos2> = stroke.points.iter().map(|p| to_screen * *p).collect();
+                    let widths: Vec<f32> = stroke.widths.iter().map(|w| w * scale).collect();
+                    draw_tapered(painter, &points, &widths, stroke.color);
                 } else if stroke.points.len() >= 2 {
                     for window in stroke.points.windows(2) {
-                        let p1 = window[0];
-                        let p2 = window[1];
-                        painter.line_segment([p1, p2], Stroke::new(stroke.size, stroke.color));
+                        let p1 = to_screen * window[0];
+                        let p2 = to_screen * window[1];
+                        painter.line_segment([p1, p2], Stroke::new(stroke.size * scale, stroke.color));
                     }
                 }
             }
+            StrokeType::Fill => {
+                painter.add(fill_shape(stroke.fill_rects().map(|r| to_screen.transform_rect(r)), stroke.color));
+            }
         }
     }
     
@@ -109,13 +145,17 @@ impl PaintingApp {
             return;
         }
 
+        // lay the points out at the on-screen canvas size first, stroke sizes follow its width
+        let layout_rect = self.canvas_rect.unwrap_or(thumb_rect);
+        let to_screen = canvas_to_screen(layout_rect);
+
         let mut min_x = f32::MAX;
         let mut min_y = f32::MAX;
         let mut max_x = f32::MIN;
         let mut max_y = f32::MIN;
 
         for stroke in strokes {
-            for point in &stroke.points {
+            for point in stroke.points.iter().map(|p| to_screen * *p) {
                 min_x = min_x.min(point.x);
                 min_y = min_y.min(point.y);
                 max_x = max_x.max(point.x);
@@ -137,16 +177,18 @@ impl PaintingApp {
             let scale_x = thumb_width / content_width;
             let scale_y = thumb_height / content_height;
             let scale = scale_x.min(scale_y);
+            let size_scale = layout_rect.width() * scale;
 
             let content_scaled_width = content_width * scale;
             let content_scaled_height = content_height * scale;
             let x_offset = thumb_rect.min.x + (thumb_width - content_scaled_width) / 2.0;
             let y_offset = thumb_rect.min.y + (thumb_height - content_scaled_height) / 2.0;
 
-            for stroke in strokes {
+            for stroke in in_paint_order(strokes) {
                 let scaled_points: Vec<Pos2> = stroke
                     .points
                     .iter()
+                    .map(|point| to_screen * *point)
                     .map(|point| {
                         Pos2::new(
                             x_offset + (point.x - min_x) * scale,
@@ -159,19 +201,50 @@ impl PaintingApp {
                     StrokeType::Draw => {
                         if scaled_points.len() < 2 {
                             if let Some(point) = scaled_points.first() {
-                                painter.circle_filled(*point, stroke.size * scale * 0.5, stroke.color);
+                                painter.circle_filled(*point, stroke.width_at(0) * size_scale * 0.5, stroke.color);
                             }
+                        } else if stroke.is_tapered() {
+                            let widths: Vec<f32> = stroke.widths.iter().map(|w| w * size_scale).collect();
+                            draw_tapered(painter, &scaled_points, &widths, stroke.color);
                         } else {
                             for window in scaled_points.windows(2) {
                                 painter.line_segment(
                                     [window[0], window[1]],
-                                    Stroke::new(stroke.size * scale, stroke.color),
+                                    Stroke::new(stroke.size * size_scale, stroke.color),
                                 );
                             }
                         }
                     }
+                    StrokeType::Fill => {
+                        let rects = scaled_points
+                            .chunks_exact(2)
+                            .map(|corners| Rect::from_two_pos(corners[0], corners[1]));
+                        painter.add(fill_shape(rects, stroke.color));
+                    }
                 }
             }
         }
     }
-}
\ No newline at end of file
+}
+
+// trapezoids between neighbouring points with a round dot on every point to close the joints
+fn draw_tapered(painter: &egui::Painter, points: &[Pos2], widths: &[f32], color: Color32) {
+    for (window, w) in points.windows(2).zip(widths.windows(2)) {
+        if window[0] != window[1] {
+            let quad = taper_segment(window[0], w[0], window[1], w[1]);
+            painter.add(Shape::convex_polygon(quad.to_vec(), color, Stroke::NONE));
+        }
+    }
+    for (point, width) in points.iter().zip(widths) {
+        painter.circle_filled(*point, width / 2.0, color);
+    }
+}
+
+// a single mesh without feathering, so neighbouring spans don't leave seams
+fn fill_shape(rects: impl Iterator<Item = Rect>, color: Color32) -> Shape {
+    let mut mesh = Mesh::default();
+    for rect in rects {
+        mesh.add_colored_rect(rect, color);
+    }
+    Shape::mesh(mesh)
+}
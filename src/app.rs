@@ -1,15 +1,26 @@
-use eframe::egui::{self, Color32, Key, Pos2, Rect, Vec2, RichText, FontId, FontFamily};
-use crate::models::{Stroke, Notification};
+use eframe::egui::{self, emath::TSTransform, Color32, Key, Pos2, Rect, Vec2, RichText, FontId, FontFamily};
+use crate::models::{Stroke, StrokeType, Notification};
 use crate::ui::{draw_left_panel, draw_frame_panel, draw_canvas};
-use crate::utils::{distance_to_line_segment, get_local_ip_address};
-use crate::input::InputHandler;
+use crate::utils::{canvas_to_screen, distance_to_line_segment, get_local_ip_address};
+use crate::input::{ContactTool, DeviceStatus, InputHandler, TouchInput};
+use crate::pointer::{EguiPointerTracker, PointerId};
+use crate::smoothing::{SmoothingSettings, Stabilizer};
+use crate::gestures::GestureTracker;
+use crate::palm::{PalmFilter, PalmRejectionSettings};
+use crate::calibration::{CalibrationSession, CalibrationStore, TouchTransform};
+use crate::export::{ExportJob, ExportTarget};
+use crate::history::{EditCommand, History, HISTORY_LIMIT};
+use crate::recording::InputRecorder;
+use crate::latency::LatencyStats;
 use std::collections::HashMap;
+use std::sync::Arc;
 use crate::models::Stroke as DrawingStroke;
 
-#[derive(PartialEq, Clone)]
+#[derive(PartialEq, Clone, Copy)]
 pub enum ToolMode {
     Brush,
-    Eraser
+    Eraser,
+    Fill
 }
 
 pub struct PaintingApp {
@@ -23,15 +34,16 @@ pub struct PaintingApp {
     pub next_onion_color: Color32,
     pub copied_frame: Option<Vec<Stroke>>,
     pub export_url: String,
+    pub export_size: Option<[u32; 2]>,
+    pub export_target: ExportTarget,
+    pub export_dir: String,
     pub canvas_aspect_ratio: f32,
     pub canvas_rect: Option<Rect>,
     pub playing_animation: bool,
     pub animation_speed: f32,
     pub last_frame_time: f64,
-    pub original_canvas_rect: Option<Rect>,
 
-    pub undo_history: Vec<(Vec<Vec<Stroke>>, Rect)>,
-    pub redo_history: Vec<(Vec<Vec<Stroke>>, Rect)>,
+    pub history: History,
     pub tool_mode: ToolMode,
 
     pub left_panel_open: bool,
@@ -45,59 +57,175 @@ pub struct PaintingApp {
     pub last_export_time: f64,
 
     pub input_handler: Option<InputHandler>,
-    pub active_touches: HashMap<u32, DrawingStroke>,
-    pub invert_input: bool,
+    pub active_touches: HashMap<PointerId, DrawingStroke>,
+    // touches read this frame that the canvas and frame strip still have to handle
+    pub pending_touches: Vec<TouchInput>,
+    // the grabbed touch currently standing in for the mouse on the panels
+    pub pointer_touch: Option<PointerId>,
+    pub egui_pointers: EguiPointerTracker,
+    pub stabilizers: HashMap<PointerId, Stabilizer>,
+    pub smoothing: SmoothingSettings,
+    // zoom and pan of the canvas on screen, see view.rs
+    pub view: TSTransform,
+    pub gestures: GestureTracker,
+    // where the view was when the current gesture began, taps and swipes put it back
+    pub view_before_gesture: Option<TSTransform>,
     pub target_position: Pos2,
+
+    pub project_path: String,
+
+    pub frame_thumb_rects: Vec<Rect>,
+    pub frame_drag: Option<FrameDrag>,
+    // where a hovering pen is, for the brush-size cursor preview
+    pub hover_cursor: Option<(Pos2, ContactTool)>,
+    pub palm_filter: PalmFilter,
+    pub calibration: Option<CalibrationSession>,
+    pub calibrations: CalibrationStore,
+    pub calibration_path: String,
+    pub latency: LatencyStats,
+}
+
+pub const MAX_FRAMES: usize = 32;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum FrameDragPhase {
+    Pressed,
+    Dragging,
+    Cancelled,
+}
+
+pub struct FrameDrag {
+    pub touch_id: Option<PointerId>, // None when dragged with the egui pointer
+    pub from: usize,
+    pub started_at: f64,
+    pub start_pos: Pos2,
+    pub pos: Pos2,
+    pub phase: FrameDragPhase,
+}
+
+impl FrameDrag {
+    pub fn new(touch_id: Option<PointerId>, from: usize, pos: Pos2, now: f64) -> Self {
+        Self {
+            touch_id,
+            from,
+            started_at: now,
+            start_pos: pos,
+            pos,
+            phase: FrameDragPhase::Pressed,
+        }
+    }
+}
+
+pub struct AppOptions {
+    pub input_device_paths: Vec<String>,
+    pub grab_input: bool,
+    // write every raw event and touch to this file
+    pub record_input: Option<String>,
+    // read touches from a recording instead of the devices
+    pub replay_input: Option<String>,
+    // orientation of panels without a calibration
+    pub touch_transform: TouchTransform,
+    pub target_position: Pos2,
+    pub load_path: Option<String>,
+    pub export_size: Option<[u32; 2]>,
+    pub frame_count: usize,
+    pub calibrate: bool,
+    pub calibration_path: String,
+    pub show_latency: bool,
+}
+
+#[cfg(test)]
+impl AppOptions {
+    // no devices and nothing loaded, for tests that drive the app by hand
+    pub fn headless() -> Self {
+        Self {
+            input_device_paths: Vec::new(),
+            grab_input: false,
+            record_input: None,
+            replay_input: None,
+            touch_transform: Default::default(),
+            target_position: Pos2::ZERO,
+            load_path: None,
+            export_size: None,
+            frame_count: 8,
+            calibrate: false,
+            calibration_path: "tests/recordings/no-calibration.json".to_string(),
+            show_latency: false,
+        }
+    }
 }
 
 impl PaintingApp {
-    pub fn new(input_device_path_option: Option<String>, invert_input: bool, target_position: Pos2) -> Self {
+    // `ctx` is woken by the input threads whenever touches arrive
+    pub fn new(ctx: &egui::Context, options: AppOptions) -> Self {
+        let AppOptions {
+            input_device_paths,
+            grab_input,
+            record_input,
+            replay_input,
+            touch_transform,
+            target_position,
+            load_path,
+            export_size,
+            frame_count,
+            calibrate,
+            calibration_path,
+            show_latency,
+        } = options;
+
         let mut frames = Vec::new();
-        for _ in 0..8 {
+        for _ in 0..frame_count.clamp(1, MAX_FRAMES) {
             frames.push(Vec::new());
         }
 
-        let input_handler = match input_device_path_option {
-            Some(path_str) => {
-                match InputHandler::new(&path_str) {
-                    Ok(handler) => {
-                        log::info!("InputHandler initialized successfully with device: {}.", path_str);
-                        Some(handler)
-                    }
-                    Err(e) => {
-                        log::error!("Failed to initialize InputHandler with device {}: {}. Touch input may be disabled.", path_str, e);
-                        None
-                    }
+        let calibrations = CalibrationStore::load(&calibration_path).unwrap_or_else(|e| {
+            log::error!("Failed to read calibrations from {}: {}. Touch input will be uncalibrated.", calibration_path, e);
+            CalibrationStore::default()
+        });
+
+        let mut input_handler = if let Some(path) = replay_input {
+            match InputHandler::replay(&path, Some(ctx.clone())) {
+                Ok(handler) => Some(handler),
+                Err(e) => {
+                    log::error!("Failed to replay input from {}: {}. Touch input is disabled.", path, e);
+                    None
                 }
             }
-            None => {
-                let default_path = "/dev/input/by-id/usb-Elo_Touch_Solutions_Elo_Touch_Solutions_Pcap_USB_Interface-event-if00";
-                log::warn!(
-                    "No input device path provided via --input. Attempting to use default path: {}",
-                    default_path
-                );
-                match InputHandler::new(default_path) {
-                    Ok(handler) => {
-                        log::info!("InputHandler initialized successfully with default device.");
-                        Some(handler)
-                    }
-                    Err(e) => {
-                        log::warn!(
-                            "Failed to initialize InputHandler with default device: {}. Touch input may be disabled. Consider providing an input device path via --input if touch is not working.",
-                            e
-                        );
-                        None
-                    }
+        } else if !input_device_paths.is_empty() {
+            let recorder = record_input.and_then(|path| match InputRecorder::create(&path) {
+                Ok(recorder) => Some(Arc::new(recorder)),
+                Err(e) => {
+                    log::error!("Failed to create input recording {}: {}. Input will not be recorded.", path, e);
+                    None
+                }
+            });
+            match InputHandler::new(&input_device_paths, &calibrations, grab_input, recorder, Some(ctx.clone())) {
+                Ok(handler) => {
+                    log::info!("InputHandler initialized successfully with devices: {}.", input_device_paths.join(", "));
+                    Some(handler)
+                }
+                Err(e) => {
+                    log::error!("Failed to initialize InputHandler: {}. Touch input may be disabled.", e);
+                    None
                 }
             }
+        } else {
+            log::warn!(
+                "No touch device found. Touch input is disabled. Run with --list-input-devices to see what is available and pick one with --input or --input-name."
+            );
+            None
         };
+        if let Some(handler) = input_handler.as_mut() {
+            log::info!("Uncalibrated touch devices are {}", touch_transform.label());
+            handler.set_touch_transform(touch_transform);
+        }
 
         let local_ip = get_local_ip_address();
         if local_ip.is_none() {
             log::warn!("Could not determine local IP address. Admin link will use a default (127.0.0.1).");
         }
 
-        Self {
+        let mut app = Self {
             brush_color: Color32::BLACK,
             brush_size: 5.0,
             frames,
@@ -108,14 +236,15 @@ impl PaintingApp {
             next_onion_color: Color32::BLUE,
             copied_frame: None,
             export_url: "http://localhost:1337/upload".to_string(),
+            export_size,
+            export_target: ExportTarget::Server,
+            export_dir: "exports".to_string(),
             canvas_aspect_ratio: 3.0 / 4.0,
             canvas_rect: None,
             playing_animation: false,
             animation_speed: 10.0,
             last_frame_time: 0.0,
-            original_canvas_rect: None,
-            undo_history: Vec::new(),  
-            redo_history: Vec::new(),
+            history: History::new(HISTORY_LIMIT),
             tool_mode: ToolMode::Brush,
             notifications: Vec::new(),
             next_notification_id: 0,
@@ -124,21 +253,51 @@ impl PaintingApp {
             last_export_time: 0.0,
             input_handler,
             active_touches: HashMap::new(),
+            pending_touches: Vec::new(),
+            pointer_touch: None,
+            egui_pointers: EguiPointerTracker::default(),
+            stabilizers: HashMap::new(),
+            smoothing: SmoothingSettings::default(),
+            view: TSTransform::IDENTITY,
+            gestures: GestureTracker::default(),
+            view_before_gesture: None,
             left_panel_open: false,
-            invert_input,
             target_position,
             show_admin_panel: false,
             local_ip_address: local_ip,
+            project_path: load_path.clone().unwrap_or_else(|| "project.json".to_string()),
+            frame_thumb_rects: Vec::new(),
+            frame_drag: None,
+            hover_cursor: None,
+            palm_filter: PalmFilter::new(PalmRejectionSettings::default()),
+            calibration: None,
+            calibrations,
+            calibration_path,
+            latency: LatencyStats::new(show_latency),
+        };
+
+        if calibrate {
+            app.start_calibration();
         }
+
+        if let Some(path) = load_path {
+            if let Err(e) = app.load_project(&path) {
+                log::error!("Failed to load project {}: {}. Starting with empty frames.", path, e);
+            }
+        }
+
+        app
     }
 }
 
 impl eframe::App for PaintingApp {
+    fn raw_input_hook(&mut self, ctx: &egui::Context, raw_input: &mut egui::RawInput) {
+        self.route_touch_input(ctx, raw_input);
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         ctx.set_pixels_per_point(1.2);
 
-        self.update_notifications(ctx);
-
         let current_pos = ctx.input(|i| i.viewport().clone()).outer_rect.unwrap().min;
         if (current_pos.x - self.target_position.x).abs() > 300.0 || (current_pos.y - self.target_position.y).abs() > 100.0 {
             log::warn!(
@@ -150,19 +309,37 @@ impl eframe::App for PaintingApp {
 
         log::info!("Actual window position: ({}, {})", current_pos.x, current_pos.y);
 
+        self.show(ctx);
+    }
+}
+
+impl PaintingApp {
+    // one frame of the app without the window management, so it also runs headless
+    pub fn show(&mut self, ctx: &egui::Context) {
+        self.latency.frame(ctx.input(|i| i.time));
+        self.update_notifications(ctx);
+        self.update_input_devices(ctx);
+
+
         if self.exporting {
             self.exporting = false;
             
-            let frames = self.frames.clone();
-            let export_url = self.export_url.clone();
-            let canvas_rect = self.canvas_rect.clone();
+            let job = ExportJob {
+                frames: self.frames.clone(),
+                target: self.export_target,
+                export_url: self.export_url.clone(),
+                export_dir: self.export_dir.clone(),
+                canvas_rect: self.canvas_rect,
+                export_size: self.export_size,
+                fps: self.animation_speed,
+            };
             
             let ctx_clone = ctx.clone();
             let next_id = self.next_notification_id;
             self.next_notification_id += 1;
             
             std::thread::spawn(move || {
-                Self::export_animation_threaded(frames, export_url, canvas_rect, next_id, ctx_clone);
+                Self::export_animation_threaded(job, next_id, ctx_clone);
             });
         }
 
@@ -211,96 +388,105 @@ impl eframe::App for PaintingApp {
             draw_canvas(self, ui);
         });
 
+        self.draw_calibration_overlay(ctx);
         self.draw_notifications(ctx);
+        self.draw_latency_overlay(ctx);
     }
 }
 
 impl PaintingApp {
-    pub fn save_state_for_undo(&mut self) {
-        if let Some(current_original_rect) = self.original_canvas_rect {
-            let current_state = self.frames.clone();
-            self.undo_history.push((current_state, current_original_rect));
-            self.redo_history.clear();
-
-            if self.undo_history.len() > 30 {
-                self.undo_history.remove(0);
-            }
-        } else {
-            log::warn!("[Undo] Attempted to save state for undo, but original_canvas_rect is None.");
-        }
+    pub fn execute(&mut self, command: EditCommand) {
+        self.history.execute(&mut self.frames, command);
     }
 
     pub fn undo(&mut self) {
-        if let Some((previous_frames_state, historical_original_rect)) = self.undo_history.pop() {
-            if let Some(current_original_rect) = self.original_canvas_rect {
-                self.redo_history.push((self.frames.clone(), current_original_rect));
-
-                self.frames = previous_frames_state;
-
-                if let Some(current_canvas_rect_for_drawing) = self.canvas_rect {
-                    log::info!(
-                        "[Undo] Recalculating strokes from historical_basis: {:?} to current_canvas: {:?}",
-                        historical_original_rect,
-                        current_canvas_rect_for_drawing
-                    );
-                    self.recalculate_strokes_relative_to(historical_original_rect, current_canvas_rect_for_drawing);
-                    self.original_canvas_rect = Some(current_canvas_rect_for_drawing);
-                } else {
-                    log::warn!("[Undo] canvas_rect is None during undo. Strokes might be misaligned.");
-                    self.original_canvas_rect = Some(historical_original_rect);
-                }
-            } else {
-                 log::warn!("[Undo] original_canvas_rect is None. Cannot properly save current state for redo.");
-                 self.undo_history.push((previous_frames_state, historical_original_rect));
-            }
+        if self.history.undo(&mut self.frames) {
+            self.clamp_current_frame();
         }
     }
 
     pub fn redo(&mut self) {
-        if let Some((next_frames_state, historical_original_rect)) = self.redo_history.pop() {
-             if let Some(current_original_rect) = self.original_canvas_rect {
-                self.undo_history.push((self.frames.clone(), current_original_rect));
+        if self.history.redo(&mut self.frames) {
+            self.clamp_current_frame();
+        }
+    }
 
-                self.frames = next_frames_state;
+    pub fn clear_current_frame(&mut self) {
+        let strokes = self.frames[self.current_frame].clone();
+        self.execute(EditCommand::ClearFrame { frame: self.current_frame, strokes });
+    }
 
-                if let Some(current_canvas_rect_for_drawing) = self.canvas_rect {
-                     log::info!(
-                        "[Redo] Recalculating strokes from historical_basis: {:?} to current_canvas: {:?}",
-                        historical_original_rect,
-                        current_canvas_rect_for_drawing
-                    );
-                    self.recalculate_strokes_relative_to(historical_original_rect, current_canvas_rect_for_drawing);
-                    self.original_canvas_rect = Some(current_canvas_rect_for_drawing);
-                } else {
-                    log::warn!("[Redo] canvas_rect is None during redo. Strokes might be misaligned.");
-                    self.original_canvas_rect = Some(historical_original_rect);
-                }
-            } else {
-                log::warn!("[Redo] original_canvas_rect is None. Cannot properly save current state for undo.");
-                self.redo_history.push((next_frames_state, historical_original_rect));
-            }
+    pub fn clear_all_frames(&mut self) {
+        self.current_frame = 0;
+        let frames = self.frames.clone();
+        self.execute(EditCommand::ClearAllFrames { frames });
+    }
+
+    fn clamp_current_frame(&mut self) {
+        self.current_frame = self.current_frame.min(self.frames.len().saturating_sub(1));
+    }
+
+    pub fn insert_frame_after_current(&mut self) {
+        if self.frames.len() >= MAX_FRAMES {
+            log::warn!("[Frames] Cannot insert frame, already at the maximum of {} frames.", MAX_FRAMES);
+            return;
+        }
+        self.current_frame += 1;
+        self.execute(EditCommand::InsertFrame { index: self.current_frame, strokes: Vec::new() });
+    }
+
+    pub fn duplicate_current_frame(&mut self) {
+        if self.frames.len() >= MAX_FRAMES {
+            log::warn!("[Frames] Cannot duplicate frame, already at the maximum of {} frames.", MAX_FRAMES);
+            return;
         }
+        let copy = self.frames[self.current_frame].clone();
+        self.current_frame += 1;
+        self.execute(EditCommand::InsertFrame { index: self.current_frame, strokes: copy });
     }
 
-    pub fn recalculate_strokes_relative_to(&mut self, from_basis: Rect, to_basis: Rect) {
-        if from_basis.width() <= 0.0 || from_basis.height() <= 0.0 || to_basis.width() <= 0.0 || to_basis.height() <= 0.0 {
-            log::warn!("[Recalculate] Invalid basis rect(s) provided. From: {:?}, To: {:?}. Skipping recalculation.", from_basis, to_basis);
+    pub fn delete_current_frame(&mut self) {
+        if self.frames.len() <= 1 {
+            log::warn!("[Frames] Cannot delete the last remaining frame.");
             return;
         }
+        let strokes = self.frames[self.current_frame].clone();
+        self.execute(EditCommand::DeleteFrame { index: self.current_frame, strokes });
+        self.clamp_current_frame();
+    }
 
-        for frame_strokes in &mut self.frames {
-            for stroke in frame_strokes {
-                for point in &mut stroke.points {
-                    let rel_x = (point.x - from_basis.min.x) / from_basis.width();
-                    let rel_y = (point.y - from_basis.min.y) / from_basis.height();
+    pub fn frame_drop_index(&self, pos: Pos2) -> Option<usize> {
+        self.frame_thumb_rects
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.center().distance(pos).total_cmp(&b.center().distance(pos)))
+            .map(|(i, _)| i)
+    }
 
-                    point.x = to_basis.min.x + (rel_x * to_basis.width());
-                    point.y = to_basis.min.y + (rel_y * to_basis.height());
+    pub fn finish_frame_drag(&mut self, drag: FrameDrag) {
+        match drag.phase {
+            FrameDragPhase::Pressed => {
+                self.current_frame = drag.from.min(self.frames.len() - 1);
+                self.playing_animation = false;
+            }
+            FrameDragPhase::Dragging => {
+                if let Some(to) = self.frame_drop_index(drag.pos) {
+                    log::info!("[FrameStrip] Moving frame {} to {}", drag.from, to);
+                    self.move_frame(drag.from, to);
                 }
             }
+            FrameDragPhase::Cancelled => {}
         }
     }
 
+    pub fn move_frame(&mut self, from: usize, to: usize) {
+        if from == to || from >= self.frames.len() || to >= self.frames.len() {
+            return;
+        }
+        self.execute(EditCommand::MoveFrame { from, to });
+        self.current_frame = to;
+    }
+
     pub fn calculate_aspect_ratio_rect(&self, available_rect: Rect) -> Rect {
         let available_width = available_rect.width();
         let available_height = available_rect.height();
@@ -352,23 +538,32 @@ impl PaintingApp {
     }
 
     pub fn paste_to_current_frame(&mut self) {
-        let copied_frame = if let Some(ref copied) = self.copied_frame {
-            Some(copied.clone())
-        } else {
-            None
-        };
-        
-        if let Some(frame) = copied_frame {
-            self.save_state_for_undo();
-            self.frames[self.current_frame] = frame;
+        if let Some(after) = self.copied_frame.clone() {
+            let before = self.frames[self.current_frame].clone();
+            self.execute(EditCommand::PasteFrame { frame: self.current_frame, before, after });
         }
     }
 
-    pub fn erase_strokes_at_position(&mut self, pos: Pos2, radius: f32) {
+    // `contact`'s erasing is taken back if it gets cancelled, see `discard_contact_edits`
+    pub fn erase_strokes_at_position(&mut self, pos: Pos2, radius: f32, contact: PointerId) {
+        let Some(canvas_rect) = self.canvas_rect else {
+            log::warn!("[Eraser] canvas_rect is None. Cannot erase.");
+            return;
+        };
+        let to_screen = canvas_to_screen(canvas_rect);
         let mut to_remove = Vec::new();
 
         for (i, stroke) in self.frames[self.current_frame].iter().enumerate() {
-            for point in &stroke.points {
+            if stroke.stroke_type == StrokeType::Fill {
+                if stroke.fill_rects().any(|rect| to_screen.transform_rect(rect).expand(radius).contains(pos)) {
+                    to_remove.push(i);
+                }
+                continue;
+            }
+
+            let screen_points: Vec<Pos2> = stroke.points.iter().map(|p| to_screen * *p).collect();
+
+            for point in &screen_points {
                 let distance = point.distance(pos);
                 if distance <= radius {
                     to_remove.push(i);
@@ -376,8 +571,8 @@ impl PaintingApp {
                 }
             }
 
-            if !to_remove.contains(&i) && stroke.points.len() >= 2 {
-                for window in stroke.points.windows(2) {
+            if !to_remove.contains(&i) && screen_points.len() >= 2 {
+                for window in screen_points.windows(2) {
                     let p1 = window[0];
                     let p2 = window[1];
 
@@ -390,13 +585,50 @@ impl PaintingApp {
             }
         }
 
-        if !to_remove.is_empty() {
-            self.save_state_for_undo();
+        if to_remove.is_empty() {
+            return;
+        }
+        to_remove.sort_unstable();
+        to_remove.dedup();
+        let frame = self.current_frame;
+        let removed = to_remove
+            .into_iter()
+            .map(|i| (i, self.frames[frame][i].clone()))
+            .collect();
+        self.history.execute_for(&mut self.frames, EditCommand::RemoveStrokes { frame, removed }, Some(contact));
+    }
+
+    // takes back what a contact erased once it turns out to be a palm or the start of a gesture.
+    // Other contacts' edits stay, and if one of them builds on this contact's nothing is taken back
+    pub fn discard_contact_edits(&mut self, id: PointerId) {
+        match self.history.discard_contact(&mut self.frames, id) {
+            Some(0) => {}
+            Some(count) => {
+                self.clamp_current_frame();
+                log::debug!("[Canvas] Discarded {} edits of cancelled contact {:?}", count, id);
+            }
+            None => log::debug!("[Canvas] Kept the edits of cancelled contact {:?}, later edits depend on them", id),
+        }
+    }
 
-            to_remove.sort_unstable();
-            to_remove.dedup();
-            for i in to_remove.into_iter().rev() {
-                self.frames[self.current_frame].remove(i);
+    fn update_input_devices(&mut self, ctx: &egui::Context) {
+        let Some(handler) = &self.input_handler else {
+            return;
+        };
+
+        for status in handler.get_status_events() {
+            match status {
+                DeviceStatus::Disconnected { name } => {
+                    self.add_notification(
+                        format!("Touch screen disconnected ({}). Waiting for it to come back...", name),
+                        Color32::RED,
+                        8.0,
+                        ctx,
+                    );
+                }
+                DeviceStatus::Reconnected { name } => {
+                    self.add_notification(format!("Touch screen reconnected ({})", name), Color32::GREEN, 3.0, ctx);
+                }
             }
         }
     }
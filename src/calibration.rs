@@ -0,0 +1,392 @@
+use crate::app::PaintingApp;
+use crate::input::{TouchInput, TouchState};
+use crate::ui::touch_to_screen;
+use eframe::egui::{self, Color32, Pos2, Rect, Stroke};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::Path;
+
+// crosshair targets in screen space (0-1), inset from the edges where panels are least accurate
+pub const CALIBRATION_TARGETS: [Pos2; 5] = [
+    Pos2::new(0.15, 0.15),
+    Pos2::new(0.85, 0.15),
+    Pos2::new(0.85, 0.85),
+    Pos2::new(0.15, 0.85),
+    Pos2::new(0.5, 0.5),
+];
+
+// kiosks have no Esc key, so the overlay has a button below the crosshairs to back out
+fn calibration_cancel_rect(screen_rect: Rect) -> Rect {
+    Rect::from_center_size(screen_rect.lerp_inside(egui::vec2(0.5, 0.93)), egui::vec2(220.0, 60.0))
+}
+
+/// Maps a device position (0-1 over its axes) to the screen (0-1 over the window):
+/// `x' = a*x + b*y + c`, `y' = d*x + e*y + f`. Covers rotation, mirroring, offset and skew.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Affine {
+    pub matrix: [f32; 6],
+}
+
+impl Affine {
+    pub fn apply(&self, p: Pos2) -> Pos2 {
+        let [a, b, c, d, e, f] = self.matrix;
+        Pos2::new(a * p.x + b * p.y + c, d * p.x + e * p.y + f)
+    }
+
+    /// Least-squares fit through the (raw, screen) pairs, needs at least three points that
+    /// aren't on one line.
+    pub fn solve(pairs: &[(Pos2, Pos2)]) -> Result<Self, Box<dyn Error>> {
+        if pairs.len() < 3 {
+            return Err(format!("need at least 3 calibration points, got {}", pairs.len()).into());
+        }
+
+        // normal equations, shared by both output axes
+        let mut ata = [[0.0f64; 3]; 3];
+        let mut atx = [0.0f64; 3];
+        let mut aty = [0.0f64; 3];
+        for (raw, screen) in pairs {
+            let row = [raw.x as f64, raw.y as f64, 1.0];
+            for i in 0..3 {
+                for j in 0..3 {
+                    ata[i][j] += row[i] * row[j];
+                }
+                atx[i] += row[i] * screen.x as f64;
+                aty[i] += row[i] * screen.y as f64;
+            }
+        }
+
+        let [a, b, c] = solve_3x3(ata, atx).ok_or("calibration points are on one line, touch each crosshair")?;
+        let [d, e, f] = solve_3x3(ata, aty).ok_or("calibration points are on one line, touch each crosshair")?;
+        Ok(Self { matrix: [a as f32, b as f32, c as f32, d as f32, e as f32, f as f32] })
+    }
+}
+
+/// How uncalibrated panels are mounted: turned clockwise by `rotation` degrees (0, 90, 180 or
+/// 270), then mirrored. A calibration already covers this, so it only applies without one.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TouchTransform {
+    pub rotation: u16,
+    pub flip_x: bool,
+    pub flip_y: bool,
+}
+
+impl TouchTransform {
+    pub fn affine(&self) -> Affine {
+        let [a, b, c, d, e, f] = match self.rotation {
+            90 => [0.0, -1.0, 1.0, 1.0, 0.0, 0.0],
+            180 => [-1.0, 0.0, 1.0, 0.0, -1.0, 1.0],
+            270 => [0.0, 1.0, 0.0, -1.0, 0.0, 1.0],
+            _ => [1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+        };
+        // mirroring an axis is x' = 1 - x' on the rotated result
+        let (a, b, c) = if self.flip_x { (-a, -b, 1.0 - c) } else { (a, b, c) };
+        let (d, e, f) = if self.flip_y { (-d, -e, 1.0 - f) } else { (d, e, f) };
+        Affine { matrix: [a, b, c, d, e, f] }
+    }
+
+    pub fn label(&self) -> String {
+        let mut parts = vec![format!("rotated {}°", self.rotation)];
+        if self.flip_x {
+            parts.push("flipped X".to_string());
+        }
+        if self.flip_y {
+            parts.push("flipped Y".to_string());
+        }
+        parts.join(", ")
+    }
+}
+
+fn solve_3x3(m: [[f64; 3]; 3], v: [f64; 3]) -> Option<[f64; 3]> {
+    let det = |m: [[f64; 3]; 3]| {
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1]) - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    };
+    let d = det(m);
+    if d.abs() < 1e-9 {
+        return None;
+    }
+
+    // Cramer's rule, plenty for a 3x3
+    let mut result = [0.0; 3];
+    for (col, value) in result.iter_mut().enumerate() {
+        let mut replaced = m;
+        for row in 0..3 {
+            replaced[row][col] = v[row];
+        }
+        *value = det(replaced) / d;
+    }
+    Some(result)
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DeviceCalibration {
+    pub device_name: String,
+    pub affine: Affine,
+}
+
+/// Calibrations keyed by device identity, see `InputHandler::device_key`.
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct CalibrationStore {
+    pub devices: BTreeMap<String, DeviceCalibration>,
+}
+
+impl CalibrationStore {
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        if !Path::new(path).exists() {
+            return Ok(Self::default());
+        }
+        let json = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let json = serde_json::to_string_pretty(self)?;
+        let tmp_path = format!("{}.tmp", path);
+        std::fs::write(&tmp_path, json)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    pub fn get(&self, device_key: &str) -> Option<Affine> {
+        self.devices.get(device_key).map(|c| c.affine)
+    }
+}
+
+pub struct CalibrationSession {
+    // the first device touched owns the session, touches from other devices are ignored
+    pub device: Option<usize>,
+    pub points: Vec<Pos2>,
+}
+
+impl CalibrationSession {
+    pub fn new() -> Self {
+        Self { device: None, points: Vec::new() }
+    }
+}
+
+impl PaintingApp {
+    pub fn start_calibration(&mut self) {
+        if self.input_handler.is_none() {
+            log::error!("[Calibration] No touch device to calibrate.");
+            return;
+        }
+        log::info!("[Calibration] Started");
+        self.active_touches.clear();
+        self.calibration = Some(CalibrationSession::new());
+    }
+
+    pub fn handle_calibration_touch(&mut self, event: &TouchInput, screen_rect: Rect, ctx: &egui::Context) {
+        let Some(session) = self.calibration.as_mut() else {
+            return;
+        };
+        if event.state != TouchState::Ended {
+            return;
+        }
+        // any device may cancel, through whatever mapping it has now
+        if calibration_cancel_rect(screen_rect).contains(touch_to_screen(screen_rect, event)) {
+            self.cancel_calibration();
+            return;
+        }
+        if session.device.is_some_and(|d| d != event.device) {
+            return;
+        }
+
+        session.device = Some(event.device);
+        session.points.push(event.pos);
+        log::info!(
+            "[Calibration] Target {}/{}: raw ({:.3},{:.3})",
+            session.points.len(), CALIBRATION_TARGETS.len(), event.pos.x, event.pos.y
+        );
+
+        if session.points.len() == CALIBRATION_TARGETS.len() {
+            let session = self.calibration.take().expect("session checked above");
+            self.finish_calibration(session, ctx);
+        }
+    }
+
+    pub fn cancel_calibration(&mut self) {
+        log::info!("[Calibration] Cancelled");
+        self.calibration = None;
+    }
+
+    fn finish_calibration(&mut self, session: CalibrationSession, ctx: &egui::Context) {
+        let (Some(device), Some(handler)) = (session.device, self.input_handler.as_ref()) else {
+            return;
+        };
+        let pairs: Vec<(Pos2, Pos2)> = session.points.iter().copied().zip(CALIBRATION_TARGETS).collect();
+
+        let affine = match Affine::solve(&pairs) {
+            Ok(affine) => affine,
+            Err(e) => {
+                log::error!("[Calibration] Failed to solve: {}", e);
+                self.add_notification(format!("Calibration failed: {}", e), Color32::RED, 4.0, ctx);
+                return;
+            }
+        };
+        log::info!("[Calibration] Solved matrix {:?} for {}", affine.matrix, handler.device_key(device));
+
+        handler.set_calibration(device, Some(affine));
+        self.calibrations.devices.insert(
+            handler.device_key(device).to_string(),
+            DeviceCalibration { device_name: handler.device_name(device).to_string(), affine },
+        );
+
+        match self.calibrations.save(&self.calibration_path) {
+            Ok(()) => self.add_notification(
+                format!("Calibration saved to {}", self.calibration_path),
+                Color32::GREEN,
+                3.0,
+                ctx,
+            ),
+            Err(e) => {
+                log::error!("[Calibration] Failed to save {}: {}", self.calibration_path, e);
+                self.add_notification(format!("Calibration applied but not saved: {}", e), Color32::RED, 4.0, ctx);
+            }
+        }
+    }
+
+    pub fn clear_calibration(&mut self, device: usize, ctx: &egui::Context) {
+        let Some(handler) = self.input_handler.as_ref() else {
+            return;
+        };
+        handler.set_calibration(device, None);
+        self.calibrations.devices.remove(handler.device_key(device));
+        log::info!("[Calibration] Cleared calibration for {}", handler.device_key(device));
+
+        if let Err(e) = self.calibrations.save(&self.calibration_path) {
+            log::error!("[Calibration] Failed to save {}: {}", self.calibration_path, e);
+            self.add_notification(format!("Could not save calibrations: {}", e), Color32::RED, 4.0, ctx);
+        }
+    }
+
+    pub fn draw_calibration_overlay(&mut self, ctx: &egui::Context) {
+        let Some(session) = self.calibration.as_ref() else {
+            return;
+        };
+        let screen_rect = ctx.screen_rect();
+        // an interactable area on top so the touches don't also press the buttons underneath
+        let cancel_rect = calibration_cancel_rect(screen_rect);
+        let (painter, cancel) = egui::Area::new(egui::Id::new("calibration"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(screen_rect.min)
+            .show(ctx, |ui| {
+                ui.allocate_rect(screen_rect, egui::Sense::click_and_drag());
+                let cancel = ui.interact(cancel_rect, egui::Id::new("calibration_cancel"), egui::Sense::click());
+                (ui.painter().clone(), cancel)
+            })
+            .inner;
+        painter.rect_filled(screen_rect, 0.0, Color32::BLACK);
+
+        let index = session.points.len();
+        let target = screen_rect.min + CALIBRATION_TARGETS[index].to_vec2() * screen_rect.size();
+        let stroke = Stroke::new(2.0, Color32::WHITE);
+        painter.line_segment([target - egui::vec2(30.0, 0.0), target + egui::vec2(30.0, 0.0)], stroke);
+        painter.line_segment([target - egui::vec2(0.0, 30.0), target + egui::vec2(0.0, 30.0)], stroke);
+        painter.circle_stroke(target, 12.0, Stroke::new(2.0, Color32::RED));
+
+        painter.text(
+            screen_rect.center() + egui::vec2(0.0, 80.0),
+            egui::Align2::CENTER_CENTER,
+            format!("Touch the center of the crosshair ({}/{})", index + 1, CALIBRATION_TARGETS.len()),
+            egui::FontId::proportional(22.0),
+            Color32::WHITE,
+        );
+
+        let fill = if cancel.hovered() { Color32::from_gray(90) } else { Color32::from_gray(60) };
+        painter.rect(cancel_rect, 8.0, fill, Stroke::new(1.0, Color32::WHITE));
+        painter.text(
+            cancel_rect.center(),
+            egui::Align2::CENTER_CENTER,
+            "Cancel (Esc)",
+            egui::FontId::proportional(22.0),
+            Color32::WHITE,
+        );
+
+        if cancel.clicked() || ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.cancel_calibration();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::AppOptions;
+    use crate::input::{ContactTool, InputHandler, ScriptedSource};
+    use std::time::{Duration, Instant};
+
+    fn maps(transform: TouchTransform, from: (f32, f32), to: (f32, f32)) -> bool {
+        transform.affine().apply(Pos2::new(from.0, from.1)).distance(Pos2::new(to.0, to.1)) < 1e-6
+    }
+
+    #[test]
+    fn rotations_match_the_old_portrait_mappings() {
+        let rotated = |rotation| TouchTransform { rotation, ..Default::default() };
+        // what --invert did on a portrait window
+        assert!(maps(rotated(90), (0.2, 0.7), (0.3, 0.2)));
+        // and the portrait default without it
+        assert!(maps(rotated(270), (0.2, 0.7), (0.7, 0.8)));
+        assert!(maps(rotated(180), (0.2, 0.7), (0.8, 0.3)));
+        assert!(maps(rotated(0), (0.2, 0.7), (0.2, 0.7)));
+    }
+
+    #[test]
+    fn flips_mirror_after_rotating() {
+        let transform = TouchTransform { rotation: 90, flip_x: true, flip_y: false };
+        assert!(maps(transform, (0.2, 0.7), (0.7, 0.2)));
+        let transform = TouchTransform { rotation: 0, flip_x: false, flip_y: true };
+        assert!(maps(transform, (0.2, 0.7), (0.2, 0.3)));
+        let transform = TouchTransform { rotation: 180, flip_x: true, flip_y: true };
+        assert!(maps(transform, (0.2, 0.7), (0.2, 0.7)));
+    }
+
+    fn tap(x: f32, y: f32) -> [TouchInput; 2] {
+        // a fingertip the palm filter would call too large, lifted in the same report
+        let began = TouchInput {
+            id: 1,
+            pos: Pos2::new(x, y),
+            state: TouchState::Began,
+            pressure: None,
+            contact_size: Some(0.9),
+            tool: ContactTool::Finger,
+            device: 0,
+            screen_pos: None,
+            timestamp: None,
+        };
+        [began, TouchInput { state: TouchState::Ended, ..began }]
+    }
+
+    fn route_until(app: &mut PaintingApp, ctx: &egui::Context, done: impl Fn(&PaintingApp) -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while !done(app) && Instant::now() < deadline {
+            let mut input = egui::RawInput {
+                screen_rect: Some(Rect::from_min_size(Pos2::ZERO, egui::vec2(1000.0, 1000.0))),
+                ..Default::default()
+            };
+            app.route_touch_input(ctx, &mut input);
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn calibration_taps_skip_the_palm_filter_and_cancel_works_by_touch() {
+        let ctx = egui::Context::default();
+        let mut app = PaintingApp::new(&ctx, AppOptions::headless());
+        let mut source = ScriptedSource::default().device("script", Vec::new());
+        for target in &CALIBRATION_TARGETS[..2] {
+            let [down, up] = tap(target.x, target.y);
+            source = source.touch(down).touch(up);
+        }
+        let [down, up] = tap(0.5, 0.93);
+        app.input_handler = Some(InputHandler::with_source(source.wait(Duration::from_millis(200)).touch(down).touch(up), None));
+        app.playing_animation = true;
+        app.start_calibration();
+
+        route_until(&mut app, &ctx, |app| app.calibration.as_ref().is_none_or(|s| s.points.len() == 2));
+        assert_eq!(app.calibration.as_ref().map(|s| s.points.len()), Some(2));
+        route_until(&mut app, &ctx, |app| app.calibration.is_none());
+        assert!(app.calibration.is_none());
+        assert!(app.pending_touches.is_empty());
+    }
+}
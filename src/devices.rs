@@ -0,0 +1,230 @@
+use evdev::{AbsoluteAxisCode, Device, KeyCode, PropType};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// What the user asked for on the command line, every given field has to match.
+#[derive(Default, Debug)]
+pub struct DeviceSelector {
+    // case-insensitive substring of the device name
+    pub name: Option<String>,
+    pub vendor_product: Option<(u16, u16)>,
+    // substring of the physical path, e.g. usb-0000:00:14.0-3/input0
+    pub phys: Option<String>,
+}
+
+impl DeviceSelector {
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.vendor_product.is_none() && self.phys.is_none()
+    }
+
+    fn matches(&self, device: &DiscoveredDevice) -> bool {
+        let name_ok = self
+            .name
+            .as_ref()
+            .is_none_or(|pattern| device.name.to_lowercase().contains(&pattern.to_lowercase()));
+        let id_ok = self
+            .vendor_product
+            .is_none_or(|(vendor, product)| device.vendor == vendor && device.product == product);
+        let phys_ok = self
+            .phys
+            .as_ref()
+            .is_none_or(|pattern| device.phys.as_deref().is_some_and(|p| p.contains(pattern.as_str())));
+        name_ok && id_ok && phys_ok
+    }
+}
+
+pub struct DiscoveredDevice {
+    pub path: PathBuf,
+    // stable /dev/input/by-id link to the same node, when udev made one
+    pub by_id: Option<PathBuf>,
+    pub name: String,
+    pub vendor: u16,
+    pub product: u16,
+    pub phys: Option<String>,
+    // INPUT_PROP_DIRECT, the device is a screen rather than a touchpad
+    pub direct: bool,
+    pub multitouch: bool,
+    pub pen: bool,
+}
+
+impl DiscoveredDevice {
+    fn from_device(path: PathBuf, device: &Device, by_id: Option<PathBuf>) -> Self {
+        let id = device.input_id();
+        let axes = device.supported_absolute_axes();
+        let has_axis = |axis| axes.is_some_and(|a| a.contains(axis));
+        Self {
+            path,
+            by_id,
+            name: device.name().unwrap_or("Unknown Device").to_string(),
+            vendor: id.vendor(),
+            product: id.product(),
+            phys: device.physical_path().map(str::to_string),
+            direct: device.properties().contains(PropType::DIRECT),
+            multitouch: has_axis(AbsoluteAxisCode::ABS_MT_POSITION_X) && has_axis(AbsoluteAxisCode::ABS_MT_POSITION_Y),
+            pen: has_axis(AbsoluteAxisCode::ABS_X)
+                && device.supported_keys().is_some_and(|keys| keys.contains(KeyCode::BTN_TOOL_PEN)),
+        }
+    }
+
+    pub fn is_touchscreen(&self) -> bool {
+        self.direct && (self.multitouch || self.pen)
+    }
+
+    // the by-id link survives replugging, the eventN node doesn't
+    pub fn preferred_path(&self) -> String {
+        self.by_id.as_ref().unwrap_or(&self.path).display().to_string()
+    }
+}
+
+fn by_id_links() -> HashMap<PathBuf, PathBuf> {
+    let mut links = HashMap::new();
+    let Ok(entries) = std::fs::read_dir("/dev/input/by-id") else {
+        return links;
+    };
+    for entry in entries.flatten() {
+        if let Ok(target) = std::fs::canonicalize(entry.path()) {
+            links.insert(target, entry.path());
+        }
+    }
+    links
+}
+
+pub fn discover_devices() -> Vec<DiscoveredDevice> {
+    let links = by_id_links();
+    let mut devices: Vec<DiscoveredDevice> = evdev::enumerate()
+        .map(|(path, device)| {
+            let by_id = links.get(&path).cloned();
+            DiscoveredDevice::from_device(path, &device, by_id)
+        })
+        .collect();
+    devices.sort_by(|a, b| a.path.cmp(&b.path));
+    devices
+}
+
+/// Device paths to open: the ones matching the selector, or every touchscreen when it's empty.
+pub fn select_devices(selector: &DeviceSelector) -> Vec<String> {
+    let devices = discover_devices();
+    let selected = filter_devices(&devices, selector);
+
+    for device in &selected {
+        log::info!("[Devices] Selected {} ({}) at {}", device.name, describe_id(device), device.preferred_path());
+    }
+    if selected.is_empty() {
+        log::warn!("[Devices] No touch device matched {:?} among {} input devices", selector, devices.len());
+    }
+    selected.iter().map(|d| d.preferred_path()).collect()
+}
+
+// a selector may pick indirect devices, but never ones without touch or pen axes
+fn filter_devices<'a>(devices: &'a [DiscoveredDevice], selector: &DeviceSelector) -> Vec<&'a DiscoveredDevice> {
+    if selector.is_empty() {
+        devices.iter().filter(|d| d.is_touchscreen()).collect()
+    } else {
+        devices.iter().filter(|d| (d.multitouch || d.pen) && selector.matches(d)).collect()
+    }
+}
+
+fn describe_id(device: &DiscoveredDevice) -> String {
+    format!("{:04x}:{:04x}", device.vendor, device.product)
+}
+
+pub fn print_device_list() {
+    let devices = discover_devices();
+    if devices.is_empty() {
+        println!("No input devices found. Is this user in the 'input' group?");
+        return;
+    }
+
+    for device in &devices {
+        let mut kinds = Vec::new();
+        if device.direct {
+            kinds.push("direct");
+        }
+        if device.multitouch {
+            kinds.push("multitouch");
+        }
+        if device.pen {
+            kinds.push("pen");
+        }
+        println!(
+            "{} {}\n    name:  {}\n    id:    {}\n    phys:  {}\n    by-id: {}\n    kind:  {}",
+            if device.is_touchscreen() { "*" } else { " " },
+            device.path.display(),
+            device.name,
+            describe_id(device),
+            device.phys.as_deref().unwrap_or("-"),
+            device.by_id.as_deref().map_or("-".to_string(), |p| p.display().to_string()),
+            if kinds.is_empty() { "-".to_string() } else { kinds.join(", ") },
+        );
+    }
+    println!("\n* picked automatically when no --input or selector option is given");
+}
+
+pub fn parse_vendor_product(value: &str) -> Result<(u16, u16), String> {
+    let (vendor, product) = value
+        .split_once(':')
+        .ok_or_else(|| format!("expected VENDOR:PRODUCT in hex, got '{}'", value))?;
+    let parse = |part: &str| u16::from_str_radix(part.trim().trim_start_matches("0x"), 16).map_err(|e| format!("invalid id '{}': {}", part, e));
+    Ok((parse(vendor)?, parse(product)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vendor_product_ids_parse_as_hex() {
+        assert_eq!(parse_vendor_product("056a:0100"), Ok((0x056a, 0x0100)));
+        assert_eq!(parse_vendor_product("0x056A:0x0100"), Ok((0x056a, 0x0100)));
+        assert_eq!(parse_vendor_product(" 222a : 1 "), Ok((0x222a, 0x0001)));
+        assert_eq!(parse_vendor_product("ffff:0"), Ok((0xffff, 0)));
+    }
+
+    #[test]
+    fn malformed_vendor_product_ids_are_errors() {
+        for value in ["", "056a", "056a-0100", "056a:", ":0100", "056g:0100", "10000:0100", "056a:0100:1"] {
+            assert!(parse_vendor_product(value).is_err(), "'{}' parsed", value);
+        }
+        assert!(parse_vendor_product("056a").unwrap_err().contains("VENDOR:PRODUCT"));
+    }
+
+    fn device(name: &str, direct: bool, multitouch: bool, pen: bool) -> DiscoveredDevice {
+        DiscoveredDevice {
+            path: PathBuf::from(format!("/dev/input/{}", name)),
+            by_id: None,
+            name: name.to_string(),
+            vendor: 0x056a,
+            product: if pen { 0x0200 } else { 0x0100 },
+            phys: Some(format!("usb-0000:00:14.0-{}/input0", name.len())),
+            direct,
+            multitouch,
+            pen,
+        }
+    }
+
+    fn names(devices: Vec<&DiscoveredDevice>) -> Vec<&str> {
+        devices.iter().map(|d| d.name.as_str()).collect()
+    }
+
+    #[test]
+    fn only_direct_touch_and_pen_devices_are_picked_automatically() {
+        let devices = [
+            device("screen", true, true, false),
+            device("touchpad", false, true, false),
+            device("pen-display", true, false, true),
+            device("keyboard", false, false, false),
+            device("direct-buttons", true, false, false),
+        ];
+        assert_eq!(names(filter_devices(&devices, &DeviceSelector::default())), vec!["screen", "pen-display"]);
+
+        // a selector can ask for an indirect device, but not for one without touch axes
+        let selector = DeviceSelector { name: Some("PAD".to_string()), ..Default::default() };
+        assert_eq!(names(filter_devices(&devices, &selector)), vec!["touchpad"]);
+        let selector = DeviceSelector { name: Some("keyboard".to_string()), ..Default::default() };
+        assert!(filter_devices(&devices, &selector).is_empty());
+
+        // every given field has to match
+        let selector = DeviceSelector { vendor_product: Some((0x056a, 0x0100)), phys: Some("-6/".to_string()), ..Default::default() };
+        assert_eq!(names(filter_devices(&devices, &selector)), vec!["screen"]);
+    }
+}
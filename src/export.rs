@@ -1,6 +1,55 @@
 use crate::app::PaintingApp;
-use eframe::egui::{self, Color32, Rect};
-use crate::models::{Stroke, Notification};
+use eframe::egui::{self, emath::RectTransform, Color32, Pos2, Rect};
+use crate::models::{Stroke, StrokeType, Notification, in_paint_order};
+use crate::utils::{canvas_to_screen, taper_outline};
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// what the panel and --export-size allow, larger pixmaps run out of memory
+pub const EXPORT_SIZE_RANGE: RangeInclusive<u32> = 16..=8192;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ExportTarget {
+    Server,
+    Gif,
+    Apng,
+    SpriteSheet,
+    Svg,
+}
+
+impl ExportTarget {
+    pub const ALL: [ExportTarget; 5] = [
+        ExportTarget::Server,
+        ExportTarget::Gif,
+        ExportTarget::Apng,
+        ExportTarget::SpriteSheet,
+        ExportTarget::Svg,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportTarget::Server => "Display Server",
+            ExportTarget::Gif => "Animated GIF",
+            ExportTarget::Apng => "Animated PNG",
+            ExportTarget::SpriteSheet => "Sprite Sheet",
+            ExportTarget::Svg => "SVG Frames",
+        }
+    }
+}
+
+pub struct ExportJob {
+    pub frames: Vec<Vec<Stroke>>,
+    pub target: ExportTarget,
+    pub export_url: String,
+    pub export_dir: String,
+    pub canvas_rect: Option<Rect>,
+    pub export_size: Option<[u32; 2]>,
+    pub fps: f32,
+}
 
 impl PaintingApp {
     pub fn start_export_animation(&mut self, ctx: &egui::Context) {
@@ -29,21 +78,10 @@ impl PaintingApp {
         self.exporting = true;
     }
 
-    pub fn export_animation_threaded(
-        frames: Vec<Vec<Stroke>>, 
-        export_url: String, 
-        canvas_rect_opt: Option<Rect>,
-        notification_id: u64,
-        ctx: egui::Context
-    ) {
-        let temp_dir = std::path::Path::new("/home/softdev/programming/project/target/release/temp_frames");
-        if !temp_dir.exists() {
-            std::fs::create_dir_all(temp_dir).expect("failed to create temp directory");
-        }
-
-        println!("exporting animation to {}", export_url);
+    pub fn export_animation_threaded(job: ExportJob, notification_id: u64, ctx: egui::Context) {
+        println!("exporting animation as {}", job.target.label());
 
-        let canvas_rect = if let Some(rect) = canvas_rect_opt {
+        let canvas_rect = if let Some(rect) = job.canvas_rect {
             rect
         } else {
             println!("err: no canvas rect found");
@@ -57,166 +95,468 @@ impl PaintingApp {
             return;
         };
 
-        let width = canvas_rect.width() as u32;
-        let height = canvas_rect.height() as u32;
+        let [width, height] = job.export_size.unwrap_or([canvas_rect.width() as u32, canvas_rect.height() as u32]);
+        let output_rect = Self::fit_export_rect(canvas_rect, width, height);
+        let to_pixels = canvas_to_screen(output_rect);
+        // stroke sizes are fractions of the canvas width, like the points they scale with it
+        let size_scale = output_rect.width();
+        println!("rendering frames at {}x{} (canvas {:.0}px wide)", width, height, size_scale);
 
-        let client = reqwest::blocking::Client::new();
-        let mut form = reqwest::blocking::multipart::Form::new();
+        let render_all = || -> Result<Vec<image::RgbaImage>, Box<dyn Error>> {
+            job.frames
+                .iter()
+                .map(|strokes| Self::render_frame(strokes, width, height, to_pixels, size_scale))
+                .collect()
+        };
 
-        for (frame_index, strokes) in frames.iter().enumerate() {
-            let mut imgbuf = image::RgbaImage::new(width, height);
+        let result = match job.target {
+            ExportTarget::Server => render_all().and_then(|rendered| Self::upload_frames(&rendered, &job.export_url)),
+            ExportTarget::Gif => Self::local_export_path(&job.export_dir, ".gif")
+                .and_then(|path| Self::write_gif(&render_all()?, job.fps, &path)),
+            ExportTarget::Apng => Self::local_export_path(&job.export_dir, ".apng.png")
+                .and_then(|path| Self::write_apng(&render_all()?, job.fps, &path)),
+            ExportTarget::SpriteSheet => Self::local_export_path(&job.export_dir, "_sheet.png")
+                .and_then(|path| Self::write_sprite_sheet(&render_all()?, &path)),
+            ExportTarget::Svg => Self::local_export_path(&job.export_dir, ".svg")
+                .map(|path| path.with_extension(""))
+                .and_then(|dir| Self::write_svg_frames(&job.frames, width, height, to_pixels, size_scale, &dir)),
+        };
 
-            for pixel in imgbuf.pixels_mut() {
-                *pixel = image::Rgba([255, 255, 255, 255]);
+        match result {
+            Ok(message) => {
+                println!("{}", message);
+                Self::add_notification_static(message, Color32::GREEN, 5.0, notification_id + 1, &ctx);
             }
+            Err(e) => {
+                println!("failed to export animation: {}", e);
+                Self::add_notification_static(
+                    format!("Export failed: {}", e),
+                    Color32::RED,
+                    5.0,
+                    notification_id + 1,
+                    &ctx,
+                );
+            }
+        }
+    }
 
-            let mut ctx_skia = tiny_skia::Pixmap::new(width, height).expect("failed to create pixmap");
-
-            let mut paint = tiny_skia::Paint::default();
-            paint.set_color(tiny_skia::Color::WHITE);
-            ctx_skia.fill_rect(
-                tiny_skia::Rect::from_xywh(0.0, 0.0, width as f32, height as f32).unwrap(),
-                &paint,
-                tiny_skia::Transform::identity(),
-                None,
-            );
+    pub fn render_frame(
+        strokes: &[Stroke],
+        width: u32,
+        height: u32,
+        to_pixels: RectTransform,
+        size_scale: f32,
+    ) -> Result<image::RgbaImage, Box<dyn Error>> {
+        let mut ctx_skia = tiny_skia::Pixmap::new(width, height)
+            .ok_or_else(|| format!("can't render a {}x{} frame", width, height))?;
 
-            for stroke in strokes {
-                if stroke.points.len() < 2 {
-                    if let Some(point) = stroke.points.first() {
-                        let mut paint = tiny_skia::Paint::default();
-                        paint.set_color(tiny_skia::Color::from_rgba8(
-                            stroke.color.r(),
-                            stroke.color.g(),
-                            stroke.color.b(),
-                            stroke.color.a(),
-                        ));
+        let mut imgbuf = image::RgbaImage::new(width, height);
 
-                        let x = point.x - canvas_rect.min.x;
-                        let y = point.y - canvas_rect.min.y;
+        for pixel in imgbuf.pixels_mut() {
+            *pixel = image::Rgba([255, 255, 255, 255]);
+        }
 
-                        let path =
-                            tiny_skia::PathBuilder::from_circle(x, y, stroke.size / 2.0).unwrap();
+        let mut paint = tiny_skia::Paint::default();
+        paint.set_color(tiny_skia::Color::WHITE);
+        ctx_skia.fill_rect(
+            tiny_skia::Rect::from_xywh(0.0, 0.0, width as f32, height as f32).unwrap(),
+            &paint,
+            tiny_skia::Transform::identity(),
+            None,
+        );
 
-                        ctx_skia.fill_path(
-                            &path,
-                            &paint,
-                            tiny_skia::FillRule::Winding,
-                            tiny_skia::Transform::identity(),
-                            None,
-                        );
-                    }
-                } else {
-                    let mut path = tiny_skia::PathBuilder::new();
-                    let mut first = true;
-
-                    for point in &stroke.points {
-                        let x = point.x - canvas_rect.min.x;
-                        let y = point.y - canvas_rect.min.y;
-
-                        if first {
-                            path.move_to(x, y);
-                            first = false;
-                        } else {
-                            path.line_to(x, y);
-                        }
+        for stroke in in_paint_order(strokes) {
+            if stroke.stroke_type == StrokeType::Fill {
+                let mut path = tiny_skia::PathBuilder::new();
+                for rect in stroke.fill_rects().map(|r| to_pixels.transform_rect(r)) {
+                    if let Some(span) = tiny_skia::Rect::from_ltrb(rect.min.x, rect.min.y, rect.max.x, rect.max.y) {
+                        path.push_rect(span);
                     }
+                }
 
-                    let path = path.finish().expect("failed to create path");
+                if let Some(path) = path.finish() {
+                    let mut paint = tiny_skia::Paint::default();
+                    paint.set_color(tiny_skia::Color::from_rgba8(
+                        stroke.color.r(),
+                        stroke.color.g(),
+                        stroke.color.b(),
+                        stroke.color.a(),
+                    ));
 
-                    let mut stroke_paint = tiny_skia::Paint::default();
-                    stroke_paint.set_color(tiny_skia::Color::from_rgba8(
+                    ctx_skia.fill_path(
+                        &path,
+                        &paint,
+                        tiny_skia::FillRule::Winding,
+                        tiny_skia::Transform::identity(),
+                        None,
+                    );
+                }
+            } else if stroke.points.len() < 2 {
+                if let Some(point) = stroke.points.first() {
+                    let mut paint = tiny_skia::Paint::default();
+                    paint.set_color(tiny_skia::Color::from_rgba8(
                         stroke.color.r(),
                         stroke.color.g(),
                         stroke.color.b(),
                         stroke.color.a(),
                     ));
 
-                    let mut stroke_style = tiny_skia::Stroke::default();
-                    stroke_style.width = stroke.size;
-                    stroke_style.line_cap = tiny_skia::LineCap::Round;
-                    stroke_style.line_join = tiny_skia::LineJoin::Round;
+                    let Pos2 { x, y } = to_pixels * *point;
+
+                    let path =
+                        tiny_skia::PathBuilder::from_circle(x, y, stroke.width_at(0) * size_scale / 2.0).unwrap();
 
-                    ctx_skia.stroke_path(
+                    ctx_skia.fill_path(
                         &path,
-                        &stroke_paint,
-                        &stroke_style,
+                        &paint,
+                        tiny_skia::FillRule::Winding,
                         tiny_skia::Transform::identity(),
                         None,
                     );
                 }
-            }
+            } else if stroke.is_tapered() {
+                let points: Vec<Pos2> = stroke.points.iter().map(|p| to_pixels * *p).collect();
+                let widths: Vec<f32> = stroke.widths.iter().map(|w| w * size_scale).collect();
+                let outline = taper_outline(&points, &widths);
+
+                let mut path = tiny_skia::PathBuilder::new();
+                for (i, point) in outline.iter().enumerate() {
+                    if i == 0 {
+                        path.move_to(point.x, point.y);
+                    } else {
+                        path.line_to(point.x, point.y);
+                    }
+                }
+                path.close();
+
+                if let Some(path) = path.finish() {
+                    let mut paint = tiny_skia::Paint::default();
+                    paint.set_color(tiny_skia::Color::from_rgba8(
+                        stroke.color.r(),
+                        stroke.color.g(),
+                        stroke.color.b(),
+                        stroke.color.a(),
+                    ));
+                    paint.anti_alias = true;
 
-            for y in 0..height {
-                for x in 0..width {
-                    let pixel = ctx_skia.pixel(x, y).unwrap();
-                    imgbuf.put_pixel(
-                        x,
-                        y,
-                        image::Rgba([
-                            pixel.red(),
-                            pixel.green(),
-                            pixel.blue(),
-                            pixel.alpha()
-                        ]),
+                    ctx_skia.fill_path(
+                        &path,
+                        &paint,
+                        tiny_skia::FillRule::Winding,
+                        tiny_skia::Transform::identity(),
+                        None,
                     );
                 }
+            } else {
+                let mut path = tiny_skia::PathBuilder::new();
+                let mut first = true;
+
+                for point in &stroke.points {
+                    let Pos2 { x, y } = to_pixels * *point;
+
+                    if first {
+                        path.move_to(x, y);
+                        first = false;
+                    } else {
+                        path.line_to(x, y);
+                    }
+                }
+
+                let Some(path) = path.finish() else {
+                    continue;
+                };
+
+                let mut stroke_paint = tiny_skia::Paint::default();
+                stroke_paint.set_color(tiny_skia::Color::from_rgba8(
+                    stroke.color.r(),
+                    stroke.color.g(),
+                    stroke.color.b(),
+                    stroke.color.a(),
+                ));
+
+                let stroke_style = tiny_skia::Stroke {
+                    width: stroke.size * size_scale,
+                    line_cap: tiny_skia::LineCap::Round,
+                    line_join: tiny_skia::LineJoin::Round,
+                    ..Default::default()
+                };
+
+                ctx_skia.stroke_path(
+                    &path,
+                    &stroke_paint,
+                    &stroke_style,
+                    tiny_skia::Transform::identity(),
+                    None,
+                );
+            }
+        }
+
+        for y in 0..height {
+            for x in 0..width {
+                let pixel = ctx_skia.pixel(x, y).unwrap();
+                imgbuf.put_pixel(
+                    x,
+                    y,
+                    image::Rgba([
+                        pixel.red(),
+                        pixel.green(),
+                        pixel.blue(),
+                        pixel.alpha()
+                    ]),
+                );
+            }
+        }
+
+        Ok(imgbuf)
+    }
+
+    fn upload_frames(rendered: &[image::RgbaImage], export_url: &str) -> Result<String, Box<dyn Error>> {
+        let temp_dir = Self::upload_temp_dir()?;
+        println!("uploading animation to {}", export_url);
+
+        let response = Self::send_frames(rendered, export_url, &temp_dir);
+        // the frame files were only needed for the request, keep them out of the temp dir either way
+        if let Err(e) = std::fs::remove_dir_all(&temp_dir) {
+            log::warn!("[Export] Failed to clean up {}: {}", temp_dir.display(), e);
+        }
+
+        let response = response?;
+        if response.status().is_success() {
+            Ok("Animation exported successfully!".to_string())
+        } else {
+            Err(format!("Server returned {}", response.status()).into())
+        }
+    }
+
+    // a fresh directory per upload, so two exports running at once never share frame files
+    fn upload_temp_dir() -> Result<PathBuf, Box<dyn Error>> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+        let base = format!("praxinoscope-upload-{}-{}", std::process::id(), timestamp);
+        let mut attempt = 1;
+        loop {
+            let dir = std::env::temp_dir().join(format!("{}-{}", base, attempt));
+            match std::fs::create_dir(&dir) {
+                Ok(()) => return Ok(dir),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => attempt += 1,
+                Err(e) => return Err(format!("can't create {}: {}", dir.display(), e).into()),
             }
+        }
+    }
+
+    fn send_frames(
+        rendered: &[image::RgbaImage],
+        export_url: &str,
+        temp_dir: &Path,
+    ) -> Result<reqwest::blocking::Response, Box<dyn Error>> {
+        let client = reqwest::blocking::Client::new();
+        let mut form = reqwest::blocking::multipart::Form::new()
+            .text("frameCount", rendered.len().to_string());
 
-            let frame_file = format!("/home/softdev/programming/project/target/release/temp_frames/{:01}.png", frame_index);
-            imgbuf
-                .save_with_format(&frame_file, image::ImageFormat::Png)
-                .expect("failed to save frame as png");
+        for (frame_index, imgbuf) in rendered.iter().enumerate() {
+            let file_name = format!("{:01}.png", frame_index);
+            let frame_file = temp_dir.join(&file_name);
+            imgbuf.save_with_format(&frame_file, image::ImageFormat::Png)?;
 
-            let file_part = reqwest::blocking::multipart::Part::file(frame_file.clone())
-                .expect("failed to create file part")
-                .file_name(format!("{:01}.png", frame_index))
-                .mime_str("image/png")
-                .expect("failed to set mime type");
+            let file_part = reqwest::blocking::multipart::Part::file(&frame_file)?
+                .file_name(file_name)
+                .mime_str("image/png")?;
 
             form = form.part(format!("{}", frame_index), file_part);
         }
 
-        match client.post(&export_url).multipart(form).send() {
-            Ok(response) => {
-                if response.status().is_success() {
-                    println!("animation exported successfully!");
-                    Self::add_notification_static(
-                        "Animation exported successfully!".to_string(),
-                        Color32::GREEN,
-                        5.0,
-                        notification_id + 1,
-                        &ctx,
-                    );
-                } else {
-                    println!(
-                        "failed to export animation. server returned: {}",
-                        response.status()
-                    );
-                    Self::add_notification_static(
-                        format!("Export failed: Server returned {}", response.status()),
-                        Color32::RED,
-                        5.0,
-                        notification_id + 1,
-                        &ctx,
-                    );
+        Ok(client.post(export_url).multipart(form).send()?)
+    }
+
+    // `suffix` tells the formats apart (".apng.png" vs "_sheet.png"), the millisecond timestamp and
+    // the counter keep quick successive exports from overwriting each other
+    fn local_export_path(export_dir: &str, suffix: &str) -> Result<PathBuf, Box<dyn Error>> {
+        std::fs::create_dir_all(export_dir)?;
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+        let mut path = Path::new(export_dir).join(format!("animation_{}{}", timestamp, suffix));
+        let mut attempt = 2;
+        // SVG exports become a directory named without the extension
+        while path.exists() || path.with_extension("").exists() {
+            path = Path::new(export_dir).join(format!("animation_{}_{}{}", timestamp, attempt, suffix));
+            attempt += 1;
+        }
+        Ok(path)
+    }
+
+    fn write_gif(rendered: &[image::RgbaImage], fps: f32, path: &Path) -> Result<String, Box<dyn Error>> {
+        let file = File::create(path)?;
+        let mut encoder = image::codecs::gif::GifEncoder::new_with_speed(file, 10);
+        encoder.set_repeat(image::codecs::gif::Repeat::Infinite)?;
+
+        let delay = image::Delay::from_numer_denom_ms(100_000, (fps * 100.0).round().max(1.0) as u32);
+        encoder.encode_frames(
+            rendered
+                .iter()
+                .map(|frame| image::Frame::from_parts(frame.clone(), 0, 0, delay)),
+        )?;
+
+        Ok(format!("Saved GIF to {}", path.display()))
+    }
+
+    fn write_apng(rendered: &[image::RgbaImage], fps: f32, path: &Path) -> Result<String, Box<dyn Error>> {
+        let first = rendered.first().ok_or("no frames to export")?;
+        let file = BufWriter::new(File::create(path)?);
+
+        let mut encoder = png::Encoder::new(file, first.width(), first.height());
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(rendered.len() as u32, 0)?;
+        encoder.set_frame_delay(100, (fps * 100.0).round().clamp(1.0, u16::MAX as f32) as u16)?;
+
+        let mut writer = encoder.write_header()?;
+        for frame in rendered {
+            writer.write_image_data(frame.as_raw())?;
+        }
+        writer.finish()?;
+
+        Ok(format!("Saved APNG to {}", path.display()))
+    }
+
+    fn write_sprite_sheet(rendered: &[image::RgbaImage], path: &Path) -> Result<String, Box<dyn Error>> {
+        let first = rendered.first().ok_or("no frames to export")?;
+        let (frame_width, frame_height) = first.dimensions();
+
+        // as close to square as possible, filled left to right then top to bottom
+        let columns = (rendered.len() as f32).sqrt().ceil() as u32;
+        let rows = (rendered.len() as u32).div_ceil(columns);
+
+        let mut sheet = image::RgbaImage::from_pixel(
+            frame_width * columns,
+            frame_height * rows,
+            image::Rgba([255, 255, 255, 255]),
+        );
+        for (i, frame) in rendered.iter().enumerate() {
+            let column = i as u32 % columns;
+            let row = i as u32 / columns;
+            image::imageops::replace(
+                &mut sheet,
+                frame,
+                (column * frame_width) as i64,
+                (row * frame_height) as i64,
+            );
+        }
+        sheet.save_with_format(path, image::ImageFormat::Png)?;
+
+        Ok(format!("Saved sprite sheet ({}x{}) to {}", columns, rows, path.display()))
+    }
+
+    // svg output skips the rasterizer, one document per frame in a folder named after the export
+    fn write_svg_frames(
+        frames: &[Vec<Stroke>],
+        width: u32,
+        height: u32,
+        to_pixels: RectTransform,
+        size_scale: f32,
+        dir: &Path,
+    ) -> Result<String, Box<dyn Error>> {
+        std::fs::create_dir_all(dir)?;
+
+        for (frame_index, strokes) in frames.iter().enumerate() {
+            let document = Self::svg_document(strokes, width, height, to_pixels, size_scale);
+            std::fs::write(dir.join(format!("frame_{:02}.svg", frame_index + 1)), document)?;
+        }
+
+        Ok(format!("Saved {} SVG frames to {}", frames.len(), dir.display()))
+    }
+
+    pub fn svg_document(
+        strokes: &[Stroke],
+        width: u32,
+        height: u32,
+        to_pixels: RectTransform,
+        size_scale: f32,
+    ) -> String {
+        let mut svg = String::new();
+        svg.push_str(&format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+            w = width,
+            h = height
+        ));
+        svg.push_str(&format!("  <rect width=\"{}\" height=\"{}\" fill=\"#ffffff\"/>\n", width, height));
+
+        for stroke in in_paint_order(strokes) {
+            let [r, g, b, a] = stroke.color.to_srgba_unmultiplied();
+            let color = format!("#{:02x}{:02x}{:02x}", r, g, b);
+            let opacity = a as f32 / 255.0;
+
+            match stroke.stroke_type {
+                StrokeType::Fill => {
+                    let mut d = String::new();
+                    for rect in stroke.fill_rects().map(|r| to_pixels.transform_rect(r)) {
+                        d.push_str(&format!(
+                            "M{:.2} {:.2}H{:.2}V{:.2}H{:.2}Z",
+                            rect.min.x, rect.min.y, rect.max.x, rect.max.y, rect.min.x
+                        ));
+                    }
+                    if !d.is_empty() {
+                        svg.push_str(&format!(
+                            "  <path d=\"{}\" fill=\"{}\" fill-opacity=\"{:.3}\"/>\n",
+                            d, color, opacity
+                        ));
+                    }
+                }
+                StrokeType::Draw => {
+                    let width = stroke.width_at(0) * size_scale;
+                    if stroke.points.len() == 1 {
+                        let center = to_pixels * stroke.points[0];
+                        svg.push_str(&format!(
+                            "  <circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\" fill=\"{}\" fill-opacity=\"{:.3}\"/>\n",
+                            center.x, center.y, width / 2.0, color, opacity
+                        ));
+                    } else if stroke.is_tapered() {
+                        let points: Vec<Pos2> = stroke.points.iter().map(|p| to_pixels * *p).collect();
+                        let widths: Vec<f32> = stroke.widths.iter().map(|w| w * size_scale).collect();
+                        let d: Vec<String> = taper_outline(&points, &widths)
+                            .iter()
+                            .enumerate()
+                            .map(|(i, p)| format!("{}{:.2} {:.2}", if i == 0 { "M" } else { "L" }, p.x, p.y))
+                            .collect();
+                        if !d.is_empty() {
+                            svg.push_str(&format!(
+                                "  <path d=\"{}Z\" fill=\"{}\" fill-opacity=\"{:.3}\" fill-rule=\"nonzero\"/>\n",
+                                d.join(" "), color, opacity
+                            ));
+                        }
+                    } else if stroke.points.len() >= 2 {
+                        let d: Vec<String> = stroke
+                            .points
+                            .iter()
+                            .enumerate()
+                            .map(|(i, point)| {
+                                let p = to_pixels * *point;
+                                format!("{}{:.2} {:.2}", if i == 0 { "M" } else { "L" }, p.x, p.y)
+                            })
+                            .collect();
+                        svg.push_str(&format!(
+                            "  <path d=\"{}\" fill=\"none\" stroke=\"{}\" stroke-opacity=\"{:.3}\" stroke-width=\"{:.2}\" stroke-linecap=\"round\" stroke-linejoin=\"round\"/>\n",
+                            d.join(" "), color, opacity, width
+                        ));
+                    }
                 }
-            }
-            Err(e) => {
-                println!("failed to export animation: {}", e);
-                Self::add_notification_static(
-                    format!("Export failed: {}", e),
-                    Color32::RED,
-                    5.0,
-                    notification_id + 1,
-                    &ctx,
-                );
             }
         }
 
-        if temp_dir.exists() {
-            std::fs::remove_dir_all(temp_dir).expect("failed to clean up temp directory");
-        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    // largest rect with the canvas aspect ratio centered in the output image, so a
+    // mismatched export size letterboxes instead of stretching the drawing
+    pub fn fit_export_rect(canvas_rect: Rect, width: u32, height: u32) -> Rect {
+        let output = Rect::from_min_size(Pos2::ZERO, egui::vec2(width as f32, height as f32));
+        let canvas_aspect = canvas_rect.width() / canvas_rect.height();
+
+        let size = if output.width() / output.height() > canvas_aspect {
+            egui::vec2(output.height() * canvas_aspect, output.height())
+        } else {
+            egui::vec2(output.width(), output.width() / canvas_aspect)
+        };
+
+        Rect::from_center_size(output.center(), size)
     }
 
     pub fn add_notification_static(message: String, color: Color32, duration: f64, id: u64, ctx: &egui::Context) {
@@ -245,4 +585,118 @@ impl ContextExt for egui::Context {
         });
         self.request_repaint();
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_exports_get_distinct_names() {
+        let dir = std::env::temp_dir().join(format!("praxinoscope-export-test-{}", std::process::id()));
+        let dir = dir.to_str().unwrap();
+
+        let apng = PaintingApp::local_export_path(dir, ".apng.png").unwrap();
+        let sheet = PaintingApp::local_export_path(dir, "_sheet.png").unwrap();
+        assert_ne!(apng, sheet);
+        assert!(apng.to_str().unwrap().ends_with(".apng.png"));
+        assert!(sheet.to_str().unwrap().ends_with("_sheet.png"));
+
+        // a second export in the same millisecond doesn't overwrite the first
+        File::create(&apng).unwrap();
+        let again = PaintingApp::local_export_path(dir, ".apng.png").unwrap();
+        assert_ne!(apng, again);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn uploads_get_their_own_temp_dir_and_clean_it_up() {
+        let first = PaintingApp::upload_temp_dir().unwrap();
+        let second = PaintingApp::upload_temp_dir().unwrap();
+        assert_ne!(first, second);
+        assert!(first.starts_with(std::env::temp_dir()) && first.is_dir() && second.is_dir());
+        std::fs::remove_dir(first).unwrap();
+        std::fs::remove_dir(second).unwrap();
+
+        // a bad url is reported, not a panic, and the frames written for it are removed
+        let frames = vec![image::RgbaImage::new(4, 4)];
+        let leftovers = || {
+            let prefix = format!("praxinoscope-upload-{}-", std::process::id());
+            std::fs::read_dir(std::env::temp_dir())
+                .unwrap()
+                .flatten()
+                .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+                .count()
+        };
+        assert!(PaintingApp::upload_frames(&frames, "not a url").is_err());
+        assert_eq!(leftovers(), 0);
+    }
+
+    #[test]
+    fn unrenderable_sizes_are_an_error() {
+        let to_pixels = canvas_to_screen(Rect::from_min_size(Pos2::ZERO, egui::vec2(64.0, 64.0)));
+        assert!(PaintingApp::render_frame(&[], 64, 64, to_pixels, 1.0).is_ok());
+        assert!(PaintingApp::render_frame(&[], 0, 64, to_pixels, 1.0).is_err());
+    }
+
+    // every coordinate pair of a path's `d` attribute
+    fn path_points(d: &str) -> Vec<Pos2> {
+        let numbers: Vec<f32> = d
+            .split(|c: char| c.is_ascii_alphabetic() || c == ' ')
+            .filter(|n| !n.is_empty())
+            .map(|n| n.parse().unwrap())
+            .collect();
+        numbers.chunks_exact(2).map(|c| Pos2::new(c[0], c[1])).collect()
+    }
+
+    #[test]
+    fn svg_document_holds_strokes_and_fills_at_the_export_size() {
+        let (width, height) = (200, 100);
+        let output_rect = Rect::from_min_size(Pos2::ZERO, egui::vec2(width as f32, height as f32));
+        let line = Stroke { points: vec![Pos2::new(0.1, 0.2), Pos2::new(0.9, 0.2)], size: 0.01, ..Default::default() };
+        let tapered = Stroke {
+            points: vec![Pos2::new(0.25, 0.5), Pos2::new(0.75, 0.5)],
+            widths: vec![0.02, 0.1],
+            ..Default::default()
+        };
+        let fill = Stroke {
+            points: vec![Pos2::new(0.25, 0.25), Pos2::new(0.5, 0.75)],
+            color: Color32::from_rgb(255, 0, 0),
+            size: 0.0,
+            stroke_type: StrokeType::Fill,
+            widths: Vec::new(),
+        };
+        let svg = PaintingApp::svg_document(&[line, tapered, fill], width, height, canvas_to_screen(output_rect), output_rect.width());
+
+        assert!(svg.contains("width=\"200\" height=\"100\" viewBox=\"0 0 200 100\""));
+        let paths: Vec<&str> = svg.lines().filter(|l| l.trim_start().starts_with("<path")).collect();
+        assert_eq!(paths.len(), 3);
+
+        // fills go first so the outlines cover their edges
+        assert!(paths[0].contains("d=\"M50.00 25.00H100.00V75.00H50.00Z\" fill=\"#ff0000\""));
+
+        assert!(paths[1].contains("d=\"M20.00 20.00 L180.00 20.00\""));
+        assert!(paths[1].contains("stroke-width=\"2.00\" stroke-linecap=\"round\" stroke-linejoin=\"round\""));
+
+        // the tapered stroke is its outline, 4px across at the start and 20px at the end
+        let d = paths[2].split("d=\"").nth(1).and_then(|rest| rest.split('"').next()).unwrap();
+        let outline = path_points(d.trim_end_matches('Z'));
+        let half_width_near = |x: f32| {
+            outline.iter().filter(|p| (p.x - x).abs() < 1.0).map(|p| (p.y - 50.0).abs()).fold(0.0, f32::max)
+        };
+        assert!((half_width_near(50.0) - 2.0).abs() < 0.1, "{}", half_width_near(50.0));
+        assert!((half_width_near(150.0) - 10.0).abs() < 0.1, "{}", half_width_near(150.0));
+    }
+
+    #[test]
+    fn stroke_widths_follow_the_export_size() {
+        let line = Stroke { points: vec![Pos2::new(0.1, 0.5), Pos2::new(0.9, 0.5)], size: 0.1, ..Default::default() };
+        let thickness = |side: u32| {
+            let output_rect = Rect::from_min_size(Pos2::ZERO, egui::vec2(side as f32, side as f32));
+            let image = PaintingApp::render_frame(std::slice::from_ref(&line), side, side, canvas_to_screen(output_rect), output_rect.width()).unwrap();
+            (0..side).filter(|&y| image.get_pixel(side / 2, y)[0] < 128).count()
+        };
+        assert!((9..=11).contains(&thickness(100)), "{}", thickness(100));
+        assert!((19..=21).contains(&thickness(200)), "{}", thickness(200));
+    }
+}
@@ -0,0 +1,214 @@
+use crate::app::PaintingApp;
+use crate::history::EditCommand;
+use crate::models::{Stroke, StrokeType};
+use crate::utils::{canvas_to_screen, distance_to_line_segment, screen_to_canvas};
+use eframe::egui::{Pos2, Rect, Vec2};
+use std::collections::{HashMap, VecDeque};
+
+// number of fill cells along the longer side of the canvas
+const FILL_GRID_RESOLUTION: f32 = 400.0;
+
+impl PaintingApp {
+    pub fn fill_at_position(&mut self, pos: Pos2) {
+        let canvas_rect = match self.canvas_rect {
+            Some(rect) => rect,
+            None => {
+                log::warn!("[Fill] canvas_rect is None. Cannot fill.");
+                return;
+            }
+        };
+
+        match compute_fill_region(&self.frames[self.current_frame], canvas_rect, pos) {
+            Some(points) => {
+                log::debug!("[Fill] Filled region made of {} spans", points.len() / 2);
+                self.execute(EditCommand::AddStroke {
+                    frame: self.current_frame,
+                    stroke: Stroke {
+                        points,
+                        color: self.brush_color,
+                        size: 0.0,
+                        stroke_type: StrokeType::Fill,
+                        widths: Vec::new(),
+                    },
+                });
+            }
+            None => {
+                log::debug!("[Fill] Nothing to fill at ({:.2},{:.2})", pos.x, pos.y);
+            }
+        }
+    }
+}
+
+/// Flood fills the area enclosed by the draw strokes around the on-screen `seed`, returning
+/// the region as (min, max) corner pairs in canvas space.
+pub fn compute_fill_region(strokes: &[Stroke], bounds: Rect, seed: Pos2) -> Option<Vec<Pos2>> {
+    if bounds.width() <= 0.0 || bounds.height() <= 0.0 || !bounds.contains(seed) {
+        return None;
+    }
+
+    let cell = bounds.width().max(bounds.height()) / FILL_GRID_RESOLUTION;
+    let cols = ((bounds.width() / cell).ceil() as usize).max(1);
+    let rows = ((bounds.height() / cell).ceil() as usize).max(1);
+
+    let cell_center = |col: usize, row: usize| {
+        bounds.min + Vec2::new((col as f32 + 0.5) * cell, (row as f32 + 0.5) * cell)
+    };
+    let cell_index = |p: Pos2| {
+        let col = (((p.x - bounds.min.x) / cell).floor().max(0.0) as usize).min(cols - 1);
+        let row = (((p.y - bounds.min.y) / cell).floor().max(0.0) as usize).min(rows - 1);
+        (col, row)
+    };
+
+    let to_screen = canvas_to_screen(bounds);
+    let mut walls = vec![false; cols * rows];
+    for stroke in strokes.iter().filter(|s| s.stroke_type == StrokeType::Draw) {
+        let screen_points: Vec<Pos2> = stroke.points.iter().map(|p| to_screen * *p).collect();
+        let segments: Vec<(usize, usize)> = if screen_points.len() == 1 {
+            vec![(0, 0)]
+        } else {
+            (1..screen_points.len()).map(|i| (i - 1, i)).collect()
+        };
+
+        for (i1, i2) in segments {
+            let (p1, p2) = (screen_points[i1], screen_points[i2]);
+            // wide enough that even a hairline leaves an unbroken wall of cells
+            let width = stroke.width_at(i1).max(stroke.width_at(i2)) * bounds.width();
+            let radius = (width / 2.0).max(cell * 0.75);
+            let (min_col, min_row) = cell_index(Pos2::new(p1.x.min(p2.x) - radius, p1.y.min(p2.y) - radius));
+            let (max_col, max_row) = cell_index(Pos2::new(p1.x.max(p2.x) + radius, p1.y.max(p2.y) + radius));
+            for row in min_row..=max_row {
+                for col in min_col..=max_col {
+                    if distance_to_line_segment(cell_center(col, row), p1, p2) <= radius {
+                        walls[row * cols + col] = true;
+                    }
+                }
+            }
+        }
+    }
+
+    let (seed_col, seed_row) = cell_index(seed);
+    if walls[seed_row * cols + seed_col] {
+        return None;
+    }
+
+    let mut region = vec![false; cols * rows];
+    let mut queue = VecDeque::new();
+    region[seed_row * cols + seed_col] = true;
+    queue.push_back((seed_col, seed_row));
+
+    while let Some((col, row)) = queue.pop_front() {
+        let mut visit = |c: usize, r: usize| {
+            let i = r * cols + c;
+            if !walls[i] && !region[i] {
+                region[i] = true;
+                queue.push_back((c, r));
+            }
+        };
+        if col > 0 { visit(col - 1, row); }
+        if col + 1 < cols { visit(col + 1, row); }
+        if row > 0 { visit(col, row - 1); }
+        if row + 1 < rows { visit(col, row + 1); }
+    }
+
+    // grow by one cell so the fill reaches underneath the surrounding outline
+    let mut grown = region.clone();
+    for row in 0..rows {
+        for col in 0..cols {
+            if !region[row * cols + col] {
+                continue;
+            }
+            if col > 0 { grown[row * cols + col - 1] = true; }
+            if col + 1 < cols { grown[row * cols + col + 1] = true; }
+            if row > 0 { grown[(row - 1) * cols + col] = true; }
+            if row + 1 < rows { grown[(row + 1) * cols + col] = true; }
+        }
+    }
+
+    // horizontal runs per row, merged with identical runs directly above them
+    let mut spans: Vec<(usize, usize, usize, usize)> = Vec::new();
+    let mut open_spans: HashMap<(usize, usize), usize> = HashMap::new();
+    for row in 0..rows {
+        let mut next_open = HashMap::new();
+        let mut col = 0;
+        while col < cols {
+            if !grown[row * cols + col] {
+                col += 1;
+                continue;
+            }
+            let start = col;
+            while col < cols && grown[row * cols + col] {
+                col += 1;
+            }
+
+            if let Some(&span_index) = open_spans.get(&(start, col)) {
+                spans[span_index].3 = row + 1;
+                next_open.insert((start, col), span_index);
+            } else {
+                spans.push((start, col, row, row + 1));
+                next_open.insert((start, col), spans.len() - 1);
+            }
+        }
+        open_spans = next_open;
+    }
+
+    let to_canvas = screen_to_canvas(bounds);
+    let points = spans
+        .into_iter()
+        .flat_map(|(col_start, col_end, row_start, row_end)| {
+            let min = bounds.min + Vec2::new(col_start as f32 * cell, row_start as f32 * cell);
+            let max = (bounds.min + Vec2::new(col_end as f32 * cell, row_end as f32 * cell)).min(bounds.max);
+            [to_canvas * min, to_canvas * max]
+        })
+        .collect();
+
+    Some(points)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds() -> Rect {
+        Rect::from_min_size(Pos2::ZERO, Vec2::splat(400.0))
+    }
+
+    fn outline(points: &[(f32, f32)]) -> Stroke {
+        Stroke { points: points.iter().map(|&(x, y)| Pos2::new(x, y)).collect(), size: 0.01, ..Default::default() }
+    }
+
+    // area in canvas space, where the whole canvas is 1
+    fn area(region: &[Pos2]) -> f32 {
+        region.chunks_exact(2).map(|c| Rect::from_two_pos(c[0], c[1]).area()).sum()
+    }
+
+    #[test]
+    fn closed_shape_fills_only_its_inside() {
+        let square = outline(&[(0.25, 0.25), (0.75, 0.25), (0.75, 0.75), (0.25, 0.75), (0.25, 0.25)]);
+        let region = compute_fill_region(&[square], bounds(), Pos2::new(200.0, 200.0)).unwrap();
+        let filled = area(&region);
+        assert!((0.2..0.3).contains(&filled), "filled {}", filled);
+        // nothing outside the outline, the fill only tucks under it
+        assert!(region.iter().all(|p| (0.2..=0.8).contains(&p.x) && (0.2..=0.8).contains(&p.y)));
+    }
+
+    #[test]
+    fn gap_in_the_outline_leaks_out() {
+        // the left side stops short of the top corner
+        let open = outline(&[(0.25, 0.35), (0.25, 0.75), (0.75, 0.75), (0.75, 0.25), (0.35, 0.25)]);
+        let region = compute_fill_region(&[open], bounds(), Pos2::new(200.0, 200.0)).unwrap();
+        assert!(area(&region) > 0.9);
+    }
+
+    #[test]
+    fn empty_canvas_fills_everything() {
+        let region = compute_fill_region(&[], bounds(), Pos2::new(10.0, 390.0)).unwrap();
+        assert!((area(&region) - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn seed_outside_the_canvas_or_on_a_line_fills_nothing() {
+        assert!(compute_fill_region(&[], bounds(), Pos2::new(-5.0, 200.0)).is_none());
+        let line = outline(&[(0.0, 0.5), (1.0, 0.5)]);
+        assert!(compute_fill_region(&[line], bounds(), Pos2::new(200.0, 200.0)).is_none());
+    }
+}
@@ -0,0 +1,341 @@
+use eframe::egui::{Pos2, Rect, Vec2};
+use crate::app::PaintingApp;
+use crate::input::{ContactTool, TouchState};
+use crate::pointer::{PointerEvent, PointerId, PointerSource};
+use std::collections::HashMap;
+
+// a tap is over quickly and its fingers stay put
+const TAP_MAX_DURATION: f64 = 0.35;
+const TAP_SLOP: f32 = 20.0;
+// both fingers travel at least this far, mostly sideways, within the time limit
+const SWIPE_MIN_DISTANCE: f32 = 100.0;
+const SWIPE_MAX_DURATION: f64 = 0.8;
+
+pub enum GestureAction {
+    // a second finger landed, the contacts are a gesture from now on
+    Started,
+    // the two fingers' midpoint moved from `from` to `to` while their spread changed by `scale`
+    Pinch { from: Pos2, to: Pos2, scale: f32 },
+    Tap { fingers: usize },
+    // the fingers moved left (forward) or right (back)
+    Swipe { forward: bool },
+}
+
+struct Contact {
+    start: Pos2,
+    pos: Pos2,
+    // fingers are taken into a gesture once a second one lands, from then on none of them draws
+    in_gesture: bool,
+}
+
+// from the first finger down until the last one lifts
+struct Session {
+    started_at: f64,
+    // most fingers down at the same time
+    fingers: usize,
+    // how far each lifted finger ended up from where it landed
+    displacements: Vec<Vec2>,
+    // a cancelled contact (palm, pen) spoils the tap or swipe
+    cancelled: bool,
+}
+
+impl Session {
+    fn recognize(&self, now: f64) -> Option<GestureAction> {
+        if self.cancelled || self.fingers < 2 {
+            return None;
+        }
+        let duration = now - self.started_at;
+        if duration <= TAP_MAX_DURATION && self.displacements.iter().all(|d| d.length() <= TAP_SLOP) {
+            return Some(GestureAction::Tap { fingers: self.fingers });
+        }
+
+        let is_swipe = |d: &Vec2| d.x.abs() >= SWIPE_MIN_DISTANCE && d.x.abs() > 2.0 * d.y.abs();
+        if self.fingers == 2 && duration <= SWIPE_MAX_DURATION && self.displacements.iter().all(is_swipe) {
+            let forward = self.displacements.iter().all(|d| d.x < 0.0);
+            let back = self.displacements.iter().all(|d| d.x > 0.0);
+            // fingers moving apart are a pinch, not a swipe
+            if forward || back {
+                return Some(GestureAction::Swipe { forward });
+            }
+        }
+        None
+    }
+}
+
+/// Watches the finger contacts on the canvas and takes them away from the drawing tools as
+/// soon as more than one is down. Pens and the mouse always pass straight through.
+#[derive(Default)]
+pub struct GestureTracker {
+    contacts: HashMap<PointerId, Contact>,
+    // in landing order, the first two drive the pinch
+    order: Vec<PointerId>,
+    session: Option<Session>,
+}
+
+impl GestureTracker {
+    pub fn process(
+        &mut self,
+        events: Vec<PointerEvent>,
+        canvas_rect: Rect,
+        now: f64,
+        actions: &mut Vec<GestureAction>,
+    ) -> Vec<PointerEvent> {
+        let mut passed = Vec::with_capacity(events.len());
+        for event in events {
+            let is_finger = event.tool == ContactTool::Finger && event.id.source != PointerSource::Mouse;
+            if !is_finger {
+                passed.push(event);
+                continue;
+            }
+
+            match event.state {
+                TouchState::Began if canvas_rect.contains(event.pos) || self.in_gesture() => {
+                    self.contacts.insert(event.id, Contact { start: event.pos, pos: event.pos, in_gesture: false });
+                    self.order.push(event.id);
+                    let session = self.session.get_or_insert(Session {
+                        started_at: now,
+                        fingers: 0,
+                        displacements: Vec::new(),
+                        cancelled: false,
+                    });
+                    session.fingers = session.fingers.max(self.contacts.len());
+                    if self.contacts.len() < 2 {
+                        passed.push(event);
+                        continue;
+                    }
+                    if !self.in_gesture() {
+                        actions.push(GestureAction::Started);
+                    }
+                    // whatever the first finger drew was the start of the gesture, not a stroke
+                    for (id, contact) in self.contacts.iter_mut().filter(|(_, c)| !c.in_gesture) {
+                        contact.in_gesture = true;
+                        if *id != event.id {
+                            passed.push(PointerEvent { id: *id, pos: contact.pos, state: TouchState::Cancelled, ..event });
+                        }
+                    }
+                    log::debug!("[Gestures] {} fingers down, drawing suspended", self.contacts.len());
+                }
+                TouchState::Moved if self.contacts.contains_key(&event.id) => {
+                    let before = self.pinch_points();
+                    if let Some(contact) = self.contacts.get_mut(&event.id) {
+                        contact.pos = event.pos;
+                        if !contact.in_gesture {
+                            passed.push(event);
+                            continue;
+                        }
+                    }
+                    if let (Some((a0, b0)), Some((a1, b1))) = (before, self.pinch_points()) {
+                        let spread = a0.distance(b0);
+                        let scale = if spread > 1.0 { a1.distance(b1) / spread } else { 1.0 };
+                        actions.push(GestureAction::Pinch { from: a0.lerp(b0, 0.5), to: a1.lerp(b1, 0.5), scale });
+                    }
+                }
+                TouchState::Ended | TouchState::Cancelled if self.contacts.contains_key(&event.id) => {
+                    let contact = self.contacts.remove(&event.id).expect("checked above");
+                    self.order.retain(|id| *id != event.id);
+                    if let Some(session) = self.session.as_mut() {
+                        session.displacements.push(event.pos - contact.start);
+                        session.cancelled |= event.state == TouchState::Cancelled;
+                    }
+                    if !contact.in_gesture {
+                        passed.push(event);
+                    }
+                    if self.contacts.is_empty() {
+                        if let Some(action) = self.session.take().and_then(|s| s.recognize(now)) {
+                            actions.push(action);
+                        }
+                    }
+                }
+                _ => passed.push(event),
+            }
+        }
+        passed
+    }
+
+    fn in_gesture(&self) -> bool {
+        self.contacts.values().any(|c| c.in_gesture)
+    }
+
+    fn pinch_points(&self) -> Option<(Pos2, Pos2)> {
+        match self.order[..] {
+            [a, b, ..] => Some((self.contacts[&a].pos, self.contacts[&b].pos)),
+            _ => None,
+        }
+    }
+}
+
+impl PaintingApp {
+    pub fn apply_gesture(&mut self, action: &GestureAction, canvas_rect: Rect) {
+        match *action {
+            GestureAction::Started => self.view_before_gesture = Some(self.view),
+            GestureAction::Pinch { from, to, scale } => self.zoom_view(from, to, scale, canvas_rect),
+            GestureAction::Tap { fingers } => {
+                // the fingers wobble a little while tapping, that shouldn't move the view
+                self.restore_view_before_gesture();
+                match fingers {
+                    2 => {
+                        log::info!("[Gestures] Two-finger tap, undo");
+                        self.undo();
+                    }
+                    3 => {
+                        log::info!("[Gestures] Three-finger tap, redo");
+                        self.redo();
+                    }
+                    _ => {}
+                }
+            }
+            GestureAction::Swipe { forward } => {
+                self.restore_view_before_gesture();
+                let count = self.frames.len();
+                self.current_frame = if forward { (self.current_frame + 1) % count } else { (self.current_frame + count - 1) % count };
+                self.playing_animation = false;
+                log::info!("[Gestures] Swipe to frame {}", self.current_frame);
+            }
+        }
+    }
+
+    fn restore_view_before_gesture(&mut self) {
+        if let Some(view) = self.view_before_gesture.take() {
+            self.view = view;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::AppOptions;
+    use crate::models::Stroke;
+    use crate::history::EditCommand;
+    use crate::view::MAX_ZOOM;
+    use eframe::egui;
+
+    fn canvas() -> Rect {
+        Rect::from_min_size(Pos2::ZERO, egui::vec2(400.0, 400.0))
+    }
+
+    fn finger(id: u64, state: TouchState, x: f32, y: f32) -> PointerEvent {
+        PointerEvent {
+            id: PointerId { source: PointerSource::Touch, id },
+            pos: Pos2::new(x, y),
+            state,
+            pressure: None,
+            contact_size: None,
+            tool: ContactTool::Finger,
+        }
+    }
+
+    fn app() -> PaintingApp {
+        PaintingApp::new(&egui::Context::default(), AppOptions::headless())
+    }
+
+    // feeds the events through the tracker and applies whatever it recognizes, returning what drew
+    fn run(tracker: &mut GestureTracker, app: &mut PaintingApp, events: Vec<PointerEvent>, now: f64) -> Vec<PointerEvent> {
+        let mut actions = Vec::new();
+        let passed = tracker.process(events, canvas(), now, &mut actions);
+        for action in &actions {
+            app.apply_gesture(action, canvas());
+        }
+        passed
+    }
+
+    fn states(events: &[PointerEvent]) -> Vec<(u64, TouchState)> {
+        events.iter().map(|e| (e.id.id, e.state)).collect()
+    }
+
+    #[test]
+    fn second_finger_cancels_the_first_fingers_stroke() {
+        use TouchState::*;
+        let mut tracker = GestureTracker::default();
+        let mut actions = Vec::new();
+
+        let passed = tracker.process(vec![finger(1, Began, 100.0, 100.0), finger(1, Moved, 110.0, 100.0)], canvas(), 0.0, &mut actions);
+        assert_eq!(states(&passed), vec![(1, Began), (1, Moved)]);
+
+        let passed = tracker.process(vec![finger(2, Began, 200.0, 100.0)], canvas(), 0.1, &mut actions);
+        assert_eq!(states(&passed), vec![(1, Cancelled)]);
+        assert_eq!(passed[0].pos, Pos2::new(110.0, 100.0));
+        assert!(matches!(actions[..], [GestureAction::Started]));
+
+        // neither finger draws again, not even after the other one lifts
+        let passed = tracker.process(
+            vec![finger(2, Ended, 200.0, 100.0), finger(1, Moved, 150.0, 100.0), finger(1, Ended, 150.0, 100.0)],
+            canvas(),
+            0.2,
+            &mut actions,
+        );
+        assert!(passed.is_empty());
+    }
+
+    #[test]
+    fn two_finger_pinch_zooms_within_the_canvas() {
+        use TouchState::*;
+        let mut app = app();
+        let mut tracker = GestureTracker::default();
+        run(&mut tracker, &mut app, vec![finger(1, Began, 150.0, 200.0), finger(2, Began, 250.0, 200.0)], 0.0);
+
+        // spreading the fingers to twice the distance zooms in twice, the canvas still covers its area
+        run(&mut tracker, &mut app, vec![finger(2, Moved, 350.0, 200.0)], 0.1);
+        assert!((app.view.scaling - 2.0).abs() < 1e-4, "{}", app.view.scaling);
+        assert!(app.view_canvas_rect(canvas()).contains_rect(canvas()));
+
+        // far past the limit, and dragged towards the corner
+        run(&mut tracker, &mut app, vec![finger(2, Moved, 3000.0, 200.0), finger(1, Moved, 0.0, 0.0)], 0.2);
+        assert_eq!(app.view.scaling, MAX_ZOOM);
+        assert!(app.view_canvas_rect(canvas()).contains_rect(canvas()));
+
+        // pinching all the way in stops at the unzoomed canvas
+        run(&mut tracker, &mut app, vec![finger(2, Moved, 1.5, 0.0)], 0.3);
+        assert_eq!(app.view.scaling, 1.0);
+        assert_eq!(app.view_canvas_rect(canvas()), canvas());
+
+        run(&mut tracker, &mut app, vec![finger(1, Ended, 0.0, 0.0), finger(2, Ended, 1.5, 0.0)], 0.4);
+        assert!(!app.is_view_zoomed());
+    }
+
+    #[test]
+    fn two_finger_tap_undoes_and_three_finger_tap_redoes() {
+        use TouchState::*;
+        let mut app = app();
+        let stroke = Stroke { points: vec![Pos2::new(0.2, 0.2), Pos2::new(0.8, 0.8)], ..Default::default() };
+        app.execute(EditCommand::AddStroke { frame: 0, stroke: stroke.clone() });
+        let mut tracker = GestureTracker::default();
+
+        run(&mut tracker, &mut app, vec![finger(1, Began, 100.0, 100.0), finger(2, Began, 200.0, 100.0)], 0.0);
+        // a little wobble is still a tap
+        run(&mut tracker, &mut app, vec![finger(1, Moved, 105.0, 102.0)], 0.05);
+        run(&mut tracker, &mut app, vec![finger(1, Ended, 105.0, 102.0), finger(2, Ended, 200.0, 100.0)], 0.2);
+        assert!(app.frames[0].is_empty());
+        assert!(!app.is_view_zoomed());
+
+        let down = (1..=3).map(|id| finger(id, Began, 100.0 * id as f32, 100.0)).collect();
+        let up = (1..=3).map(|id| finger(id, Ended, 100.0 * id as f32, 100.0)).collect();
+        run(&mut tracker, &mut app, down, 1.0);
+        run(&mut tracker, &mut app, up, 1.2);
+        assert_eq!(app.frames[0], vec![stroke]);
+
+        // too slow for a tap
+        run(&mut tracker, &mut app, vec![finger(1, Began, 100.0, 100.0), finger(2, Began, 200.0, 100.0)], 2.0);
+        run(&mut tracker, &mut app, vec![finger(1, Ended, 100.0, 100.0), finger(2, Ended, 200.0, 100.0)], 3.0);
+        assert_eq!(app.frames[0].len(), 1);
+    }
+
+    #[test]
+    fn two_finger_swipe_flips_frames() {
+        use TouchState::*;
+        let mut app = app();
+        let mut tracker = GestureTracker::default();
+        let mut swipe = |app: &mut PaintingApp, dx: f32, now: f64| {
+            run(&mut tracker, app, vec![finger(1, Began, 200.0, 150.0), finger(2, Began, 200.0, 250.0)], now);
+            run(&mut tracker, app, vec![finger(1, Moved, 200.0 + dx, 150.0), finger(2, Moved, 200.0 + dx, 250.0)], now + 0.2);
+            run(&mut tracker, app, vec![finger(1, Ended, 200.0 + dx, 150.0), finger(2, Ended, 200.0 + dx, 250.0)], now + 0.3);
+        };
+
+        swipe(&mut app, -150.0, 0.0);
+        assert_eq!(app.current_frame, 1);
+        swipe(&mut app, 150.0, 1.0);
+        swipe(&mut app, 150.0, 2.0);
+        assert_eq!(app.current_frame, app.frames.len() - 1);
+        assert!(!app.is_view_zoomed());
+    }
+}
@@ -0,0 +1,446 @@
+use crate::models::Stroke;
+use crate::pointer::PointerId;
+use std::collections::VecDeque;
+
+pub const HISTORY_LIMIT: usize = 500;
+
+// each command only keeps the strokes it touched, so history no longer copies every frame
+#[derive(Clone, Debug)]
+pub enum EditCommand {
+    AddStroke { frame: usize, stroke: Stroke },
+    // removed strokes with their original indices, ascending
+    RemoveStrokes { frame: usize, removed: Vec<(usize, Stroke)> },
+    ClearFrame { frame: usize, strokes: Vec<Stroke> },
+    PasteFrame { frame: usize, before: Vec<Stroke>, after: Vec<Stroke> },
+    ClearAllFrames { frames: Vec<Vec<Stroke>> },
+    InsertFrame { index: usize, strokes: Vec<Stroke> },
+    DeleteFrame { index: usize, strokes: Vec<Stroke> },
+    MoveFrame { from: usize, to: usize },
+}
+
+impl EditCommand {
+    pub fn apply(&self, frames: &mut Vec<Vec<Stroke>>) {
+        match self {
+            EditCommand::AddStroke { frame, stroke } => {
+                frames[*frame].push(stroke.clone());
+            }
+            EditCommand::RemoveStrokes { frame, removed } => {
+                for (index, _) in removed.iter().rev() {
+                    frames[*frame].remove(*index);
+                }
+            }
+            EditCommand::ClearFrame { frame, .. } => {
+                frames[*frame].clear();
+            }
+            EditCommand::PasteFrame { frame, after, .. } => {
+                frames[*frame] = after.clone();
+            }
+            EditCommand::ClearAllFrames { .. } => {
+                for frame in frames.iter_mut() {
+                    frame.clear();
+                }
+            }
+            EditCommand::InsertFrame { index, strokes } => {
+                frames.insert(*index, strokes.clone());
+            }
+            EditCommand::DeleteFrame { index, .. } => {
+                frames.remove(*index);
+            }
+            EditCommand::MoveFrame { from, to } => {
+                let frame = frames.remove(*from);
+                frames.insert(*to, frame);
+            }
+        }
+    }
+
+    // the one frame a command edits, None for commands that change the frame list
+    fn frame(&self) -> Option<usize> {
+        match self {
+            EditCommand::AddStroke { frame, .. }
+            | EditCommand::RemoveStrokes { frame, .. }
+            | EditCommand::ClearFrame { frame, .. }
+            | EditCommand::PasteFrame { frame, .. } => Some(*frame),
+            _ => None,
+        }
+    }
+
+    // whether `self` can still be reverted with `later` left in place. A stroke added on the same
+    // frame stays last when removed strokes are put back in front of it
+    fn independent_of(&self, later: &EditCommand) -> bool {
+        match (self.frame(), later.frame()) {
+            (Some(frame), Some(later_frame)) if frame != later_frame => true,
+            (Some(_), Some(_)) => {
+                matches!(self, EditCommand::RemoveStrokes { .. }) && matches!(later, EditCommand::AddStroke { .. })
+            }
+            _ => false,
+        }
+    }
+
+    pub fn revert(&self, frames: &mut Vec<Vec<Stroke>>) {
+        match self {
+            EditCommand::AddStroke { frame, .. } => {
+                frames[*frame].pop();
+            }
+            EditCommand::RemoveStrokes { frame, removed } => {
+                for (index, stroke) in removed {
+                    frames[*frame].insert(*index, stroke.clone());
+                }
+            }
+            EditCommand::ClearFrame { frame, strokes } => {
+                frames[*frame] = strokes.clone();
+            }
+            EditCommand::PasteFrame { frame, before, .. } => {
+                frames[*frame] = before.clone();
+            }
+            EditCommand::ClearAllFrames { frames: previous } => {
+                *frames = previous.clone();
+            }
+            EditCommand::InsertFrame { index, .. } => {
+                frames.remove(*index);
+            }
+            EditCommand::DeleteFrame { index, strokes } => {
+                frames.insert(*index, strokes.clone());
+            }
+            EditCommand::MoveFrame { from, to } => {
+                let frame = frames.remove(*to);
+                frames.insert(*from, frame);
+            }
+        }
+    }
+}
+
+// commands are tagged with the contact that made them while it's still down
+type Entry = (EditCommand, Option<PointerId>);
+
+pub struct History {
+    undo_stack: VecDeque<Entry>,
+    redo_stack: Vec<Entry>,
+    limit: usize,
+}
+
+impl History {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            limit,
+        }
+    }
+
+    pub fn execute(&mut self, frames: &mut Vec<Vec<Stroke>>, command: EditCommand) {
+        self.execute_for(frames, command, None);
+    }
+
+    // `contact` is the touch the command came from, see `discard_contact`
+    pub fn execute_for(&mut self, frames: &mut Vec<Vec<Stroke>>, command: EditCommand, contact: Option<PointerId>) {
+        command.apply(frames);
+        self.undo_stack.push_back((command, contact));
+        self.redo_stack.clear();
+
+        if self.undo_stack.len() > self.limit {
+            self.undo_stack.pop_front();
+        }
+    }
+
+    pub fn undo(&mut self, frames: &mut Vec<Vec<Stroke>>) -> bool {
+        match self.undo_stack.pop_back() {
+            Some(entry) => {
+                entry.0.revert(frames);
+                self.redo_stack.push(entry);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn redo(&mut self, frames: &mut Vec<Vec<Stroke>>) -> bool {
+        match self.redo_stack.pop() {
+            Some(entry) => {
+                entry.0.apply(frames);
+                self.undo_stack.push_back(entry);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // the contact lifted normally, its commands are ordinary history from now on
+    pub fn finish_contact(&mut self, contact: PointerId) {
+        for entry in self.undo_stack.iter_mut().chain(self.redo_stack.iter_mut()) {
+            if entry.1 == Some(contact) {
+                entry.1 = None;
+            }
+        }
+    }
+
+    /// Reverts a contact's commands for good, for edits that turn out never to have been meant.
+    /// Commands of other contacts stay, but nothing can be redone afterwards. Returns how many
+    /// were discarded, or None when a later command builds on the contact's and everything is kept.
+    pub fn discard_contact(&mut self, frames: &mut Vec<Vec<Stroke>>, contact: PointerId) -> Option<usize> {
+        let own: Vec<usize> = (0..self.undo_stack.len()).filter(|&i| self.undo_stack[i].1 == Some(contact)).collect();
+        let depends = own.iter().any(|&i| {
+            let command = &self.undo_stack[i].0;
+            self.undo_stack
+                .iter()
+                .skip(i + 1)
+                .any(|(later, owner)| *owner != Some(contact) && !command.independent_of(later))
+        });
+        if depends {
+            self.finish_contact(contact);
+            return None;
+        }
+        for &i in own.iter().rev() {
+            if let Some((command, _)) = self.undo_stack.remove(i) {
+                command.revert(frames);
+            }
+        }
+        // undone commands of the contact are already reverted, and whatever else waits to be redone
+        // was made on top of the state that's gone now
+        let undone = self.redo_stack.iter().filter(|(_, owner)| *owner == Some(contact)).count();
+        if own.len() + undone > 0 {
+            self.redo_stack.clear();
+        }
+        Some(own.len() + undone)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::StrokeType;
+    use crate::pointer::PointerSource;
+    use eframe::egui::{Color32, Pos2};
+
+    fn stroke(seed: u32) -> Stroke {
+        let t = seed as f32 / 100.0;
+        Stroke {
+            points: vec![Pos2::new(t % 1.0, 0.5), Pos2::new(0.5, (t * 3.0) % 1.0)],
+            color: Color32::from_rgb(seed as u8, 0, 0),
+            size: 0.002 * (1 + seed % 10) as f32,
+            stroke_type: StrokeType::Draw,
+            widths: Vec::new(),
+        }
+    }
+
+    // small deterministic generator so the sequences are the same on every run
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self, bound: usize) -> usize {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((self.0 >> 33) as usize) % bound
+        }
+    }
+
+    fn random_command(rng: &mut Lcg, frames: &[Vec<Stroke>], copied: &[Stroke], step: u32) -> EditCommand {
+        let frame = rng.next(frames.len());
+        match rng.next(8) {
+            0..=2 => EditCommand::AddStroke { frame, stroke: stroke(step) },
+            3 if !frames[frame].is_empty() => {
+                let removed = (0..frames[frame].len())
+                    .filter(|_| rng.next(2) == 0)
+                    .map(|i| (i, frames[frame][i].clone()))
+                    .collect();
+                EditCommand::RemoveStrokes { frame, removed }
+            }
+            4 => EditCommand::ClearFrame { frame, strokes: frames[frame].clone() },
+            5 => EditCommand::PasteFrame { frame, before: frames[frame].clone(), after: copied.to_vec() },
+            6 if frames.len() < 16 => EditCommand::InsertFrame { index: rng.next(frames.len() + 1), strokes: copied.to_vec() },
+            6 if frames.len() > 1 => EditCommand::DeleteFrame { index: frame, strokes: frames[frame].clone() },
+            7 => EditCommand::MoveFrame { from: frame, to: rng.next(frames.len()) },
+            _ => EditCommand::ClearAllFrames { frames: frames.to_vec() },
+        }
+    }
+
+    #[test]
+    fn undo_and_redo_replay_long_sequences() {
+        for seed in 0..20 {
+            let mut rng = Lcg(seed);
+            let mut history = History::new(HISTORY_LIMIT);
+            let mut frames: Vec<Vec<Stroke>> = vec![Vec::new(); 8];
+            let mut snapshots = vec![frames.clone()];
+
+            for step in 0..300 {
+                let copied = frames[rng.next(frames.len())].clone();
+                let command = random_command(&mut rng, &frames, &copied, step);
+                history.execute(&mut frames, command);
+                snapshots.push(frames.clone());
+            }
+
+            for expected in snapshots.iter().rev().skip(1) {
+                assert!(history.undo(&mut frames));
+                assert_eq!(&frames, expected);
+            }
+            assert!(!history.undo(&mut frames));
+
+            for expected in snapshots.iter().skip(1) {
+                assert!(history.redo(&mut frames));
+                assert_eq!(&frames, expected);
+            }
+            assert!(!history.redo(&mut frames));
+        }
+    }
+
+    #[test]
+    fn interleaved_undo_redo_matches_snapshots() {
+        let mut rng = Lcg(42);
+        let mut history = History::new(HISTORY_LIMIT);
+        let mut frames: Vec<Vec<Stroke>> = vec![Vec::new(); 12];
+        let mut snapshots = vec![frames.clone()];
+        let mut position = 0;
+
+        for step in 0..1000 {
+            match rng.next(4) {
+                0 if position > 0 => {
+                    assert!(history.undo(&mut frames));
+                    position -= 1;
+                }
+                1 if position + 1 < snapshots.len() => {
+                    assert!(history.redo(&mut frames));
+                    position += 1;
+                }
+                _ => {
+                    let copied = frames[rng.next(frames.len())].clone();
+                    let command = random_command(&mut rng, &frames, &copied, step);
+                    history.execute(&mut frames, command);
+                    snapshots.truncate(position + 1);
+                    snapshots.push(frames.clone());
+                    position += 1;
+                }
+            }
+            assert_eq!(frames, snapshots[position]);
+        }
+    }
+
+    #[test]
+    fn new_command_clears_redo() {
+        let mut history = History::new(HISTORY_LIMIT);
+        let mut frames: Vec<Vec<Stroke>> = vec![Vec::new(); 2];
+
+        history.execute(&mut frames, EditCommand::AddStroke { frame: 0, stroke: stroke(1) });
+        assert!(history.undo(&mut frames));
+        assert!(history.can_redo());
+
+        history.execute(&mut frames, EditCommand::AddStroke { frame: 1, stroke: stroke(2) });
+        assert!(!history.can_redo());
+        assert_eq!(frames, vec![Vec::new(), vec![stroke(2)]]);
+    }
+
+    fn contact(id: u64) -> PointerId {
+        PointerId { source: PointerSource::Evdev(0), id }
+    }
+
+    fn erase(history: &mut History, frames: &mut Vec<Vec<Stroke>>, frame: usize, index: usize, contact: PointerId) {
+        let removed = vec![(index, frames[frame][index].clone())];
+        history.execute_for(frames, EditCommand::RemoveStrokes { frame, removed }, Some(contact));
+    }
+
+    #[test]
+    fn discarded_command_cannot_be_redone() {
+        let mut history = History::new(HISTORY_LIMIT);
+        let mut frames: Vec<Vec<Stroke>> = vec![Vec::new()];
+
+        history.execute(&mut frames, EditCommand::AddStroke { frame: 0, stroke: stroke(1) });
+        history.execute_for(&mut frames, EditCommand::AddStroke { frame: 0, stroke: stroke(2) }, Some(contact(1)));
+        assert_eq!(history.discard_contact(&mut frames, contact(1)), Some(1));
+        assert_eq!(frames, vec![vec![stroke(1)]]);
+        assert!(!history.can_redo());
+
+        assert!(history.undo(&mut frames));
+        assert_eq!(frames, vec![Vec::<Stroke>::new()]);
+    }
+
+    #[test]
+    fn discarding_a_contact_keeps_the_other_contacts_edits() {
+        let mut history = History::new(HISTORY_LIMIT);
+        let mut frames: Vec<Vec<Stroke>> = vec![(0..6).map(stroke).collect(), vec![stroke(10), stroke(11)]];
+        let (palm, finger) = (contact(1), contact(2));
+
+        // the palm erases on frame 0 while a finger erases on frame 1 and another one draws
+        erase(&mut history, &mut frames, 0, 1, palm);
+        erase(&mut history, &mut frames, 1, 0, finger);
+        erase(&mut history, &mut frames, 0, 3, palm);
+        history.execute(&mut frames, EditCommand::AddStroke { frame: 0, stroke: stroke(20) });
+        erase(&mut history, &mut frames, 0, 0, palm);
+        history.finish_contact(finger);
+        assert_eq!(frames, vec![vec![stroke(2), stroke(3), stroke(5), stroke(20)], vec![stroke(11)]]);
+
+        assert_eq!(history.discard_contact(&mut frames, palm), Some(3));
+        assert_eq!(frames, vec![vec![stroke(0), stroke(1), stroke(2), stroke(3), stroke(4), stroke(5), stroke(20)], vec![stroke(11)]]);
+
+        // what's left undoes in order
+        assert!(history.undo(&mut frames));
+        assert_eq!(frames[0], (0..6).map(stroke).collect::<Vec<_>>());
+        assert!(history.undo(&mut frames));
+        assert_eq!(frames[1], vec![stroke(10), stroke(11)]);
+        assert!(!history.undo(&mut frames));
+    }
+
+    #[test]
+    fn edits_other_contacts_build_on_are_kept() {
+        let mut history = History::new(HISTORY_LIMIT);
+        let mut frames: Vec<Vec<Stroke>> = vec![(0..4).map(stroke).collect()];
+        let (palm, finger) = (contact(1), contact(2));
+
+        // the finger's indices are only right with the palm's erasing in place
+        erase(&mut history, &mut frames, 0, 0, palm);
+        erase(&mut history, &mut frames, 0, 1, finger);
+        let before = frames.clone();
+
+        assert_eq!(history.discard_contact(&mut frames, palm), None);
+        assert_eq!(frames, before);
+        // nothing is left tagged with the palm
+        assert_eq!(history.discard_contact(&mut frames, palm), Some(0));
+        assert_eq!(history.discard_contact(&mut frames, finger), Some(1));
+        assert_eq!(frames, vec![vec![stroke(1), stroke(2), stroke(3)]]);
+    }
+
+    #[test]
+    fn undone_edits_of_a_discarded_contact_cannot_be_redone() {
+        let mut history = History::new(HISTORY_LIMIT);
+        let mut frames: Vec<Vec<Stroke>> = vec![(0..4).map(stroke).collect()];
+        let palm = contact(1);
+
+        // an undo gesture while the palm is still down
+        erase(&mut history, &mut frames, 0, 2, palm);
+        erase(&mut history, &mut frames, 0, 0, palm);
+        assert!(history.undo(&mut frames));
+        assert_eq!(history.discard_contact(&mut frames, palm), Some(2));
+        assert_eq!(frames, vec![(0..4).map(stroke).collect::<Vec<_>>()]);
+
+        assert!(!history.redo(&mut frames));
+        assert!(!history.undo(&mut frames));
+        assert_eq!(frames, vec![(0..4).map(stroke).collect::<Vec<_>>()]);
+    }
+
+    #[test]
+    fn history_is_capped_at_limit() {
+        let mut history = History::new(10);
+        let mut frames: Vec<Vec<Stroke>> = vec![Vec::new()];
+
+        for i in 0..25 {
+            history.execute(&mut frames, EditCommand::AddStroke { frame: 0, stroke: stroke(i) });
+        }
+
+        let mut undone = 0;
+        while history.undo(&mut frames) {
+            undone += 1;
+        }
+        assert_eq!(undone, 10);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0], (0..15).map(stroke).collect::<Vec<_>>());
+    }
+}
@@ -1,189 +1,965 @@
-use evdev::{AbsoluteAxisCode, Device, EventType};
-use eframe::egui::Pos2;
+use evdev::{AbsoluteAxisCode, Device, EventType, InputEvent, KeyCode};
+use eframe::egui::{self, Pos2};
+use crate::calibration::{Affine, CalibrationStore, TouchTransform};
+use crate::recording::{InputRecorder, ReplaySource};
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 use std::sync::mpsc::{channel, Sender, Receiver};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::error::Error;
 
+// pens don't report tracking ids, so their contact gets one outside the kernel's 16 bit id range
+pub const PEN_CONTACT_ID: u32 = 0x8000_0000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct TouchInput {
     pub id: u32,
     pub pos: Pos2,
     pub state: TouchState,
+    // 0-1 over the device's absinfo range, None when the device doesn't report the axis
+    pub pressure: Option<f32>,
+    pub contact_size: Option<f32>,
+    pub tool: ContactTool,
+    // index into InputHandler::devices
+    pub device: usize,
+    // window position (0-1) from the device's calibration or the touch transform, filled in by
+    // InputHandler::get_events
+    pub screen_pos: Option<Pos2>,
+    // when the kernel saw the report, None for touches that didn't come from a device
+    pub timestamp: Option<SystemTime>,
 }
 
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum TouchState {
     Began,
     Moved,
     Ended,
+    // contact turned out not to be a real touch, anything it started should be discarded
+    Cancelled,
+    // pen in proximity of the screen without touching it
+    Hover,
+    HoverEnded,
 }
 
-pub struct InputHandler {
-    event_receiver: Receiver<TouchInput>,
-    thread_handle: Option<thread::JoinHandle<()>>,
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContactTool {
+    #[default]
+    Finger,
+    Pen,
+    // rubber end of the pen, or the pen with its barrel button held
+    Eraser,
 }
 
-impl InputHandler {
-    pub fn new(device_path: &str) -> Result<Self, Box<dyn Error>> {
-        log::info!("[InputHandler] Attempting to open device: {}", device_path);
-        let device = Device::open(Path::new(device_path))?;
-        log::info!("[InputHandler] Device opened successfully: {}", device.name().unwrap_or("Unknown Device"));
+// upper bound on MT slots we track, far beyond what any panel reports
+const MAX_SLOTS: usize = 32;
+
+// per-slot contact state for MT protocol B, flushed into touch events on SYN_REPORT
+#[derive(Clone, Copy, Default)]
+struct MtSlot {
+    tracking_id: Option<u32>,
+    pos: Pos2,
+    // whether Began has been sent for the current tracking id
+    announced: bool,
+    pressure: Option<f32>,
+    contact_size: Option<f32>,
+    dirty: bool,
+    // released id waiting for the next SYN, with whether its Began was ever sent
+    ending: Option<(u32, bool)>,
+}
+
+// raw range of an optional axis, used to normalize its values to 0-1
+#[derive(Clone, Copy)]
+struct AxisRange {
+    min: i32,
+    max: i32,
+}
+
+impl AxisRange {
+    fn from_absinfo(name: &str, min: i32, max: i32) -> Option<Self> {
+        if max > min {
+            log::info!("[InputHandler] Found {}: min={}, max={}", name, min, max);
+            Some(Self { min, max })
+        } else {
+            log::warn!("[InputHandler] Ignoring {} with invalid range (min: {}, max: {}).", name, min, max);
+            None
+        }
+    }
 
-        let (sender, receiver) = channel();
+    fn normalize(&self, value: i32) -> f32 {
+        ((value - self.min) as f32 / (self.max - self.min) as f32).clamp(0.0, 1.0)
+    }
+}
+
+impl MtSlot {
+    fn push(&self, id: u32, state: TouchState, out: &mut Vec<TouchInput>) {
+        log::trace!("[InputHandler] Touch {:?}: id={}, pos=({:.2},{:.2})", state, id, self.pos.x, self.pos.y);
+        out.push(TouchInput {
+            id,
+            pos: self.pos,
+            state,
+            pressure: self.pressure,
+            contact_size: self.contact_size,
+            tool: ContactTool::Finger,
+            device: 0,
+            screen_pos: None,
+            timestamp: None,
+        });
+    }
 
-        let thread_handle = thread::spawn(move || {
-            if let Err(e) = Self::read_events(device, sender) {
-                log::error!("[InputHandler] Event thread error: {}", e);
+    fn flush_ending(&mut self, out: &mut Vec<TouchInput>) {
+        if let Some((id, announced)) = self.ending.take() {
+            // a contact shorter than one report still counts as a tap
+            if !announced {
+                self.push(id, TouchState::Began, out);
             }
+            self.push(id, TouchState::Ended, out);
+        }
+    }
+
+    fn sync(&mut self, out: &mut Vec<TouchInput>) {
+        self.flush_ending(out);
+        if let Some(id) = self.tracking_id {
+            if !self.announced {
+                self.push(id, TouchState::Began, out);
+                self.push(id, TouchState::Moved, out);
+                self.announced = true;
+            } else if self.dirty {
+                self.push(id, TouchState::Moved, out);
+            }
+        }
+        self.dirty = false;
+    }
+}
+
+// single-touch ABS_X/ABS_Y state, used by pen digitizers and touchscreens without MT axes
+#[derive(Default)]
+struct SingleTouch {
+    pos: Pos2,
+    pressure: Option<f32>,
+    // tool reported in proximity through BTN_TOOL_PEN / BTN_TOOL_RUBBER
+    proximity: Option<ContactTool>,
+    stylus_button: bool,
+    touching: bool,
+    // tool of the contact in progress, fixed when it began
+    contact: Option<ContactTool>,
+    hovering: bool,
+    dirty: bool,
+}
+
+impl SingleTouch {
+    fn push(&self, state: TouchState, tool: ContactTool, out: &mut Vec<TouchInput>) {
+        log::trace!("[InputHandler] Pen {:?} ({:?}): pos=({:.2},{:.2})", state, tool, self.pos.x, self.pos.y);
+        out.push(TouchInput {
+            id: PEN_CONTACT_ID,
+            pos: self.pos,
+            state,
+            pressure: self.pressure,
+            contact_size: None,
+            tool,
+            device: 0,
+            screen_pos: None,
+            timestamp: None,
         });
+    }
 
-        Ok(Self {
-            event_receiver: receiver,
-            thread_handle: Some(thread_handle),
-        })
-    }
-
-    fn read_events(mut device: Device, sender: Sender<TouchInput>) -> Result<(), Box<dyn Error>> {
-        let mut current_touch_id: Option<u32> = None;
-        let mut current_pos = Pos2::new(0.0, 0.0);
-        let mut new_touch_began_pending_for_id: Option<u32> = None;
-
-        let mut min_x = 0;
-        let mut max_x = 1;
-        let mut min_y = 0;
-        let mut max_y = 1;
-
-        match device.get_absinfo() {
-            Ok(abs_iter) => {
-                for (axis_code, info) in abs_iter {
-                    match axis_code {
-                        AbsoluteAxisCode::ABS_MT_POSITION_X => {
-                            min_x = info.minimum();
-                            max_x = info.maximum();
-                            log::info!("[InputHandler] Found ABS_MT_POSITION_X: min={}, max={}", min_x, max_x);
-                        }
-                        AbsoluteAxisCode::ABS_MT_POSITION_Y => {
-                            min_y = info.minimum();
-                            max_y = info.maximum();
-                            log::info!("[InputHandler] Found ABS_MT_POSITION_Y: min={}, max={}", min_y, max_y);
+    fn set_proximity(&mut self, tool: ContactTool, in_range: bool) {
+        if in_range {
+            self.proximity = Some(tool);
+        } else if self.proximity == Some(tool) {
+            self.proximity = None;
+        }
+        self.dirty = true;
+    }
+
+    fn sync(&mut self, out: &mut Vec<TouchInput>) {
+        if self.touching {
+            match self.contact {
+                None => {
+                    let tool = match self.proximity {
+                        Some(ContactTool::Pen) if self.stylus_button => ContactTool::Eraser,
+                        Some(tool) => tool,
+                        None => ContactTool::Finger,
+                    };
+                    log::debug!("[InputHandler] Single-touch contact began with {:?}", tool);
+                    self.push(TouchState::Began, tool, out);
+                    self.push(TouchState::Moved, tool, out);
+                    self.contact = Some(tool);
+                    self.hovering = false;
+                }
+                Some(tool) if self.dirty => self.push(TouchState::Moved, tool, out),
+                Some(_) => {}
+            }
+        } else {
+            if let Some(tool) = self.contact.take() {
+                self.push(TouchState::Ended, tool, out);
+            }
+            match self.proximity {
+                Some(tool) if self.dirty || !self.hovering => {
+                    self.push(TouchState::Hover, tool, out);
+                    self.hovering = true;
+                }
+                None if self.hovering => {
+                    self.push(TouchState::HoverEnded, ContactTool::Pen, out);
+                    self.hovering = false;
+                }
+                _ => {}
+            }
+        }
+        self.dirty = false;
+    }
+}
+
+// absinfo of one axis, all the decoder needs to know about a device
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct AxisInfo {
+    pub code: u16,
+    pub min: i32,
+    pub max: i32,
+}
+
+pub fn device_axes(device: &Device) -> Vec<AxisInfo> {
+    match device.get_absinfo() {
+        Ok(abs_iter) => abs_iter
+            .map(|(axis_code, info)| AxisInfo { code: axis_code.0, min: info.minimum(), max: info.maximum() })
+            .collect(),
+        Err(e) => {
+            log::error!("[InputHandler] Failed to get absinfo: {}. Using default ranges 0-1.", e);
+            Vec::new()
+        }
+    }
+}
+
+// turns the raw event stream of one device into touch events
+pub struct EventDecoder {
+    mt_x: AxisRange,
+    mt_y: AxisRange,
+    pressure_range: Option<AxisRange>,
+    touch_major_range: Option<AxisRange>,
+    width_major_range: Option<AxisRange>,
+    slots: Vec<MtSlot>,
+    current_slot: usize,
+    // single-touch axes are only decoded on devices without MT, since MT panels mirror the first contact there
+    single_touch: Option<SingleTouch>,
+    st_x: AxisRange,
+    st_y: AxisRange,
+    st_pressure_range: Option<AxisRange>,
+}
+
+impl EventDecoder {
+    pub fn new(axes: &[AxisInfo]) -> Self {
+        let mut mt_x = None;
+        let mut mt_y = None;
+        let mut st_x = None;
+        let mut st_y = None;
+        let mut slot_count = 1;
+        let mut pressure_range = None;
+        let mut touch_major_range = None;
+        let mut width_major_range = None;
+        let mut st_pressure_range = None;
+
+        for axis in axes {
+            let (min, max) = (axis.min, axis.max);
+            match AbsoluteAxisCode(axis.code) {
+                AbsoluteAxisCode::ABS_MT_POSITION_X => {
+                    mt_x = AxisRange::from_absinfo("ABS_MT_POSITION_X", min, max);
+                }
+                AbsoluteAxisCode::ABS_MT_POSITION_Y => {
+                    mt_y = AxisRange::from_absinfo("ABS_MT_POSITION_Y", min, max);
+                }
+                AbsoluteAxisCode::ABS_MT_PRESSURE => {
+                    pressure_range = AxisRange::from_absinfo("ABS_MT_PRESSURE", min, max);
+                }
+                AbsoluteAxisCode::ABS_MT_TOUCH_MAJOR => {
+                    touch_major_range = AxisRange::from_absinfo("ABS_MT_TOUCH_MAJOR", min, max);
+                }
+                AbsoluteAxisCode::ABS_MT_WIDTH_MAJOR => {
+                    width_major_range = AxisRange::from_absinfo("ABS_MT_WIDTH_MAJOR", min, max);
+                }
+                AbsoluteAxisCode::ABS_MT_SLOT => {
+                    slot_count = (max + 1).clamp(1, MAX_SLOTS as i32) as usize;
+                    log::info!("[InputHandler] Found ABS_MT_SLOT: {} slots", slot_count);
+                }
+                AbsoluteAxisCode::ABS_X => {
+                    st_x = AxisRange::from_absinfo("ABS_X", min, max);
+                }
+                AbsoluteAxisCode::ABS_Y => {
+                    st_y = AxisRange::from_absinfo("ABS_Y", min, max);
+                }
+                AbsoluteAxisCode::ABS_PRESSURE => {
+                    st_pressure_range = AxisRange::from_absinfo("ABS_PRESSURE", min, max);
+                }
+                other => { log::debug!("[InputHandler] Other axis: {:?}, range {}-{}", other, min, max); }
+            }
+        }
+
+        let has_mt = mt_x.is_some() && mt_y.is_some();
+        let has_single_touch = st_x.is_some() && st_y.is_some();
+        if !has_mt && !has_single_touch {
+            log::warn!("[InputHandler] No usable position axes found. Defaulting to 0-1 for normalization.");
+        }
+        log::info!(
+            "[InputHandler] Decoding {} input",
+            if has_mt { "multitouch" } else if has_single_touch { "single-touch/pen" } else { "unknown" }
+        );
+
+        let unit = AxisRange { min: 0, max: 1 };
+        Self {
+            mt_x: mt_x.unwrap_or(unit),
+            mt_y: mt_y.unwrap_or(unit),
+            pressure_range,
+            touch_major_range,
+            width_major_range,
+            slots: vec![MtSlot::default(); slot_count],
+            current_slot: 0,
+            single_touch: (!has_mt).then(SingleTouch::default),
+            st_x: st_x.unwrap_or(unit),
+            st_y: st_y.unwrap_or(unit),
+            st_pressure_range,
+        }
+    }
+
+    // the device went away mid-contact, nothing it started should be committed
+    pub fn cancel_all(&mut self, out: &mut Vec<TouchInput>) {
+        for slot in self.slots.iter_mut() {
+            if let Some((id, true)) = slot.ending.take() {
+                slot.push(id, TouchState::Cancelled, out);
+            }
+            if let (Some(id), true) = (slot.tracking_id.take(), slot.announced) {
+                slot.push(id, TouchState::Cancelled, out);
+            }
+        }
+        if let Some(st) = self.single_touch.as_mut() {
+            if let Some(tool) = st.contact.take() {
+                st.push(TouchState::Cancelled, tool, out);
+            }
+            if st.hovering {
+                st.push(TouchState::HoverEnded, ContactTool::Pen, out);
+            }
+        }
+    }
+
+    pub fn process(&mut self, event: &InputEvent, out: &mut Vec<TouchInput>) {
+        let first = out.len();
+        self.process_event(event, out);
+        // events made up without a time (tests, old recordings) carry the epoch
+        let timestamp = Some(event.timestamp()).filter(|t| *t > UNIX_EPOCH);
+        for touch in &mut out[first..] {
+            touch.timestamp = timestamp;
+        }
+    }
+
+    fn process_event(&mut self, event: &InputEvent, out: &mut Vec<TouchInput>) {
+        match event.event_type() {
+            EventType::ABSOLUTE => self.process_absolute(AbsoluteAxisCode(event.code()), event.value(), out),
+            EventType::KEY => {
+                if let Some(st) = self.single_touch.as_mut() {
+                    let pressed = event.value() != 0;
+                    match KeyCode(event.code()) {
+                        KeyCode::BTN_TOUCH => st.touching = pressed,
+                        KeyCode::BTN_STYLUS => st.stylus_button = pressed,
+                        KeyCode::BTN_TOOL_PEN => st.set_proximity(ContactTool::Pen, pressed),
+                        KeyCode::BTN_TOOL_RUBBER => st.set_proximity(ContactTool::Eraser, pressed),
+                        _ => {}
+                    }
+                }
+            }
+            EventType::SYNCHRONIZATION => {
+                for slot in self.slots.iter_mut() {
+                    slot.sync(out);
+                }
+                if let Some(st) = self.single_touch.as_mut() {
+                    st.sync(out);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn process_absolute(&mut self, axis: AbsoluteAxisCode, value: i32, out: &mut Vec<TouchInput>) {
+        match axis {
+            AbsoluteAxisCode::ABS_MT_SLOT => {
+                let slot = value.max(0) as usize;
+                if slot >= MAX_SLOTS {
+                    log::warn!("[InputHandler] Ignoring out of range slot {}", slot);
+                    return;
+                }
+                if slot >= self.slots.len() {
+                    self.slots.resize(slot + 1, MtSlot::default());
+                }
+                self.current_slot = slot;
+            }
+            AbsoluteAxisCode::ABS_MT_TRACKING_ID => {
+                let current_slot = self.current_slot;
+                let slot = &mut self.slots[current_slot];
+                if value == -1 || value as u32 == u32::MAX {
+                    if let Some(id) = slot.tracking_id.take() {
+                        log::debug!("[InputHandler] Slot {} released id={}. Ended pending.", current_slot, id);
+                        slot.ending = Some((id, slot.announced));
+                    } else {
+                        log::warn!("[InputHandler] Received TRACKING_ID -1 for slot {} with no active contact.", current_slot);
+                    }
+                } else {
+                    let new_id = value as u32;
+                    // a release and a new contact in the same slot without a SYN in between
+                    slot.flush_ending(out);
+                    if let Some(old_id) = slot.tracking_id {
+                        if old_id != new_id {
+                            log::warn!("[InputHandler] New TRACKING_ID {} in slot {} while {} was active. Implicitly ending {}.", new_id, current_slot, old_id, old_id);
+                            slot.ending = Some((old_id, slot.announced));
+                            slot.flush_ending(out);
                         }
-                        _ => { log::debug!("[InputHandler] Other axis: {:?}, info: {:?}", axis_code, info); }
                     }
+
+                    slot.tracking_id = Some(new_id);
+                    slot.announced = false;
+                    slot.pressure = None;
+                    slot.contact_size = None;
+                    log::debug!("[InputHandler] New TRACKING_ID {} in slot {}. Began event pending.", new_id, current_slot);
+                    // DO NOT send Began event yet (wait for SYN_REPORT with updated coords)
                 }
             }
-            Err(e) => {
-                log::error!("[InputHandler] Failed to get absinfo: {}. Using default ranges 0-1.", e);
+            AbsoluteAxisCode::ABS_MT_POSITION_X => {
+                let slot = &mut self.slots[self.current_slot];
+                slot.pos.x = self.mt_x.normalize(value);
+                slot.dirty = true;
+            }
+            AbsoluteAxisCode::ABS_MT_POSITION_Y => {
+                let slot = &mut self.slots[self.current_slot];
+                slot.pos.y = self.mt_y.normalize(value);
+                slot.dirty = true;
+            }
+            AbsoluteAxisCode::ABS_MT_PRESSURE => {
+                if let Some(range) = self.pressure_range {
+                    let slot = &mut self.slots[self.current_slot];
+                    slot.pressure = Some(range.normalize(value));
+                    slot.dirty = true;
+                }
             }
+            AbsoluteAxisCode::ABS_MT_TOUCH_MAJOR => {
+                if let Some(range) = self.touch_major_range {
+                    let slot = &mut self.slots[self.current_slot];
+                    slot.contact_size = Some(range.normalize(value));
+                    slot.dirty = true;
+                }
+            }
+            // only a stand-in for panels that don't report the contact itself
+            AbsoluteAxisCode::ABS_MT_WIDTH_MAJOR if self.touch_major_range.is_none() => {
+                if let Some(range) = self.width_major_range {
+                    let slot = &mut self.slots[self.current_slot];
+                    slot.contact_size = Some(range.normalize(value));
+                    slot.dirty = true;
+                }
+            }
+            AbsoluteAxisCode::ABS_X => {
+                if let Some(st) = self.single_touch.as_mut() {
+                    st.pos.x = self.st_x.normalize(value);
+                    st.dirty = true;
+                }
+            }
+            AbsoluteAxisCode::ABS_Y => {
+                if let Some(st) = self.single_touch.as_mut() {
+                    st.pos.y = self.st_y.normalize(value);
+                    st.dirty = true;
+                }
+            }
+            AbsoluteAxisCode::ABS_PRESSURE => {
+                if let (Some(st), Some(range)) = (self.single_touch.as_mut(), self.st_pressure_range) {
+                    st.pressure = Some(range.normalize(value));
+                    st.dirty = true;
+                }
+            }
+            _ => {}
         }
-        
-        if max_x <= min_x {
-            log::warn!("[InputHandler] X_MT axis range invalid or not properly read (min: {}, max: {}). Defaulting to 0-1 for normalization.", min_x, max_x);
-            min_x = 0; max_x = 1;
+    }
+}
+
+// how often a disconnected device is looked for again
+const RECONNECT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+pub enum DeviceStatus {
+    Disconnected { name: String },
+    Reconnected { name: String },
+}
+
+// what a device looked like when it was first opened, used to find it again after a replug
+struct DeviceIdentity {
+    // as given on the command line, usually a stable /dev/input/by-id link
+    path: String,
+    name: String,
+    vendor: u16,
+    product: u16,
+    phys: Option<String>,
+}
+
+impl DeviceIdentity {
+    fn of(device: &Device, path: &str) -> Self {
+        let id = device.input_id();
+        Self {
+            path: path.to_string(),
+            name: device.name().unwrap_or("Unknown Device").to_string(),
+            vendor: id.vendor(),
+            product: id.product(),
+            phys: device.physical_path().map(str::to_string),
         }
-        if max_y <= min_y {
-            log::warn!("[InputHandler] Y_MT axis range invalid or not properly read (min: {}, max: {}). Defaulting to 0-1 for normalization.", min_y, max_y);
-            min_y = 0; max_y = 1;
+    }
+
+    fn same_model(&self, device: &Device) -> bool {
+        let id = device.input_id();
+        id.vendor() == self.vendor && id.product() == self.product && device.name().unwrap_or("Unknown Device") == self.name
+    }
+
+    fn find(&self) -> Option<(String, Device)> {
+        // the original path first, by-id links come back with the device
+        if let Ok(device) = Device::open(Path::new(&self.path)) {
+            if self.same_model(&device) {
+                return Some((self.path.clone(), device));
+            }
+        }
+
+        let mut candidates: Vec<(String, Device)> = evdev::enumerate()
+            .filter(|(_, device)| self.same_model(device))
+            .map(|(path, device)| (path.display().to_string(), device))
+            .collect();
+
+        if let Some(index) = candidates
+            .iter()
+            .position(|(_, device)| self.phys.is_some() && device.physical_path() == self.phys.as_deref())
+        {
+            return Some(candidates.swap_remove(index));
+        }
+        // on another port only when it can't be one of the other stations' identical panels
+        if candidates.len() == 1 {
+            return candidates.pop();
         }
-        log::info!("[InputHandler] Using Normalization Ranges: X({}-{}), Y({}-{})", min_x, max_x, min_y, max_y);
+        None
+    }
+}
 
+// an opened device, shared between the UI and its reader thread
+pub struct InputDevice {
+    pub name: String,
+    // vendor:product plus the physical port, so two identical panels keep separate calibrations
+    pub key: String,
+    // only read by the UI, the readers hand out raw device positions
+    calibration: Mutex<Option<Affine>>,
+    // whether the reader currently holds EVIOCGRAB, so nothing else on the system sees the touches
+    grabbed: Arc<AtomicBool>,
+}
+
+impl InputDevice {
+    pub fn new(name: String, key: String, calibration: Option<Affine>) -> Self {
+        Self {
+            name,
+            key,
+            calibration: Mutex::new(calibration),
+            grabbed: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+// what one device's reader thread needs, shared with the UI through the Arcs and channels
+struct DeviceReader {
+    identity: DeviceIdentity,
+    index: usize,
+    // Some when the device should be grabbed, set while the grab is held
+    grabbed: Option<Arc<AtomicBool>>,
+    recorder: Option<Arc<InputRecorder>>,
+    sink: InputSink,
+}
+
+impl DeviceReader {
+    // reads the device until the app goes away, waiting for it to be plugged back in whenever it drops out
+    fn run(self, mut device: Device) {
+        let identity = &self.identity;
+        loop {
+            // a replugged device is a new file handle, so the grab has to be taken again every time
+            if let Some(grabbed) = &self.grabbed {
+                Self::grab(&mut device, identity, grabbed);
+            }
+            let mut decoder = EventDecoder::new(&device_axes(&device));
+            let error = match self.read_events(&mut device, &mut decoder) {
+                Ok(()) => return,
+                Err(e) => e,
+            };
+            log::error!("[InputHandler] Lost {} ({}): {}. Waiting for it to come back.", identity.name, identity.path, error);
+            if let Some(grabbed) = &self.grabbed {
+                grabbed.store(false, Ordering::Relaxed);
+            }
+
+            let mut cancelled = Vec::new();
+            decoder.cancel_all(&mut cancelled);
+            for mut touch in cancelled {
+                touch.device = self.index;
+                if !self.sink.send_touch(touch) {
+                    return;
+                }
+            }
+            if !self.sink.send_status(DeviceStatus::Disconnected { name: identity.name.clone() }) {
+                return;
+            }
+
+            let Some(replugged) = self.wait_for_device() else {
+                return;
+            };
+            device = replugged;
+            if !self.sink.send_status(DeviceStatus::Reconnected { name: identity.name.clone() }) {
+                return;
+            }
+        }
+    }
+
+    // None once the app no longer wants input, a device that never comes back would otherwise
+    // have every event node rescanned for the rest of the process
+    fn wait_for_device(&self) -> Option<Device> {
+        loop {
+            if self.sink.is_closed() {
+                log::info!("[InputHandler] Stopped waiting for {}, input was shut down", self.identity.name);
+                return None;
+            }
+            thread::sleep(RECONNECT_POLL_INTERVAL);
+            if let Some((path, device)) = self.identity.find() {
+                log::info!("[InputHandler] {} is back at {}", self.identity.name, path);
+                return Some(device);
+            }
+        }
+    }
+
+    fn grab(device: &mut Device, identity: &DeviceIdentity, grabbed: &AtomicBool) {
+        match device.grab() {
+            Ok(()) => {
+                log::info!("[InputHandler] Grabbed {} exclusively", identity.name);
+                grabbed.store(true, Ordering::Relaxed);
+            }
+            Err(e) => {
+                // usually another process holding the grab, the desktop keeps getting the touches then
+                log::warn!("[InputHandler] Could not grab {}: {}. Touches also reach the desktop.", identity.name, e);
+                grabbed.store(false, Ordering::Relaxed);
+            }
+        }
+    }
+
+    // returns Ok once the app has dropped the receiving end, errors when the device fails
+    fn read_events(&self, device: &mut Device, decoder: &mut EventDecoder) -> Result<(), Box<dyn Error>> {
+        let mut pending = Vec::new();
 
         loop {
             for event in device.fetch_events()? {
-                match event.event_type() {
-                    EventType::ABSOLUTE => {
-                        match AbsoluteAxisCode(event.code()) {
-                            AbsoluteAxisCode::ABS_MT_TRACKING_ID => {
-                                let id_val = event.value();
-                                if id_val == -1 || id_val as u32 == u32::MAX { 
-                                    if let Some(id) = current_touch_id {
-                                        if new_touch_began_pending_for_id == Some(id) {
-                                            log::debug!("[InputHandler] Touch Ended for id={} which had a Began pending. Cancelling Began.", id);
-                                            new_touch_began_pending_for_id = None;
-                                        }
-                                        log::debug!("[InputHandler] Touch Ended by TRACKING_ID: id={}, pos=({:.2},{:.2})", id, current_pos.x, current_pos.y);
-                                        sender.send(TouchInput {
-                                            id,
-                                            pos: current_pos,
-                                            state: TouchState::Ended,
-                                        }).unwrap_or_else(|e| log::error!("Failed to send TouchEnded event: {}",e));
-                                        current_touch_id = None;
-                                    } else {
-                                        log::warn!("[InputHandler] Received TRACKING_ID -1 but no current_touch_id was set.");
-                                    }
-                                } else {
-                                    let new_id = id_val as u32;
-                                    if let Some(old_id) = current_touch_id {
-                                        if old_id != new_id {
-                                            log::warn!("[InputHandler] New TRACKING_ID {} received while {} was active. Implicitly ending {}.", new_id, old_id, old_id);
-                                            sender.send(TouchInput {
-                                                id: old_id,
-                                                pos: current_pos,
-                                                state: TouchState::Ended,
-                                            }).unwrap_or_else(|e| log::error!("Failed to send implicit TouchEnded for old_id {}: {}", old_id, e));
-                                        }
-                                    }
-                                    
-                                    current_touch_id = Some(new_id);
-                                    new_touch_began_pending_for_id = Some(new_id);
-                                    log::debug!("[InputHandler] New TRACKING_ID detected: {}. Began event pending.", new_id);
-                                    // DO NOT send Began event yet (wait for SYN_REPORT with updated coords) 
-                                }
-                            }
-                            AbsoluteAxisCode::ABS_MT_POSITION_X => {
-                                let raw_x = event.value();
-                                if max_x > min_x { // div by 0
-                                    current_pos.x = (raw_x - min_x) as f32 / (max_x - min_x) as f32;
-                                } else {
-                                    current_pos.x = 0.0;
-                                }
-                            }
-                            AbsoluteAxisCode::ABS_MT_POSITION_Y => {
-                                let raw_y = event.value();
-                                if max_y > min_y {
-                                    current_pos.y = (raw_y - min_y) as f32 / (max_y - min_y) as f32;
-                                } else {
-                                    current_pos.y = 0.0;
-                                }
-                            }
-                            _ => {}
-                        }
+                if let Some(recorder) = &self.recorder {
+                    recorder.raw(self.index, &event);
+                }
+                decoder.process(&event, &mut pending);
+                if pending.is_empty() {
+                    continue;
+                }
+
+                for mut touch in pending.drain(..) {
+                    touch.device = self.index;
+                    if let Some(recorder) = &self.recorder {
+                        recorder.touch(&touch);
+                    }
+                    if !self.sink.send_touch(touch) {
+                        log::info!("[InputHandler] Receiver dropped, stopping reader for device {}", self.index);
+                        return Ok(());
                     }
-                    EventType::SYNCHRONIZATION => {
-                        if let Some(id) = current_touch_id {
-                            if new_touch_began_pending_for_id == Some(id) {
-                                log::debug!("[InputHandler] Sending Pending Touch Began (SYN): id={}, pos=({:.2},{:.2})", id, current_pos.x, current_pos.y);
-                                sender.send(TouchInput {
-                                    id,
-                                    pos: current_pos,
-                                    state: TouchState::Began,
-                                }).unwrap_or_else(|e| log::error!("Failed to send TouchBegan event: {}",e));
-                                new_touch_began_pending_for_id = None;
-
-                                log::debug!("[InputHandler] Sending Initial Touch Moved (SYN): id={}, pos=({:.2},{:.2})", id, current_pos.x, current_pos.y);
-                                sender.send(TouchInput {
-                                    id,
-                                    pos: current_pos,
-                                    state: TouchState::Moved,
-                                }).unwrap_or_else(|e| log::error!("Failed to send initial TouchMoved event: {}",e));
-                            } else {
-                                log::trace!("[InputHandler] Touch Moved (SYN): id={}, pos=({:.2},{:.2})", id, current_pos.x, current_pos.y);
-                                sender.send(TouchInput {
-                                    id,
-                                    pos: current_pos,
-                                    state: TouchState::Moved,
-                                }).unwrap_or_else(|e| log::error!("Failed to send TouchMoved event: {}",e));
-                            }
+                }
+            }
+        }
+    }
+}
+
+/// The channel ends the sources feed. Every send wakes the UI, so touches are drawn in the next
+/// frame instead of waiting for whatever repaints it next.
+#[derive(Clone)]
+pub struct InputSink {
+    touches: Sender<TouchInput>,
+    status: Sender<DeviceStatus>,
+    repaint: Option<egui::Context>,
+    // set when the InputHandler goes away, for threads that wait rather than send
+    closed: Arc<AtomicBool>,
+}
+
+impl InputSink {
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Relaxed)
+    }
+
+    // false once the app has dropped the receiving end
+    pub fn send_touch(&self, touch: TouchInput) -> bool {
+        let sent = self.touches.send(touch).is_ok();
+        self.wake();
+        sent
+    }
+
+    pub fn send_status(&self, status: DeviceStatus) -> bool {
+        let sent = self.status.send(status).is_ok();
+        self.wake();
+        sent
+    }
+
+    fn wake(&self) {
+        if let Some(ctx) = &self.repaint {
+            ctx.request_repaint();
+        }
+    }
+}
+
+/// Where the touches of an InputHandler come from: evdev devices, a recording or a script.
+pub trait InputSource {
+    /// Starts feeding the sink. Returns the devices that `TouchInput::device` indexes into and
+    /// the threads doing the work.
+    fn start(self, sink: InputSink) -> (Vec<InputDevice>, Vec<thread::JoinHandle<()>>);
+}
+
+struct OpenedDevice {
+    device: Device,
+    identity: DeviceIdentity,
+    info: InputDevice,
+}
+
+/// The touchscreens and pen digitizers themselves, one reader thread per device.
+pub struct EvdevSource {
+    opened: Vec<OpenedDevice>,
+    // with `grab` the devices are opened exclusively and the compositor never sees their touches
+    grab: bool,
+    recorder: Option<Arc<InputRecorder>>,
+}
+
+impl EvdevSource {
+    // pen displays usually expose the pen and the touch panel as separate devices, all of them feed one channel
+    pub fn open(
+        device_paths: &[String],
+        calibrations: &CalibrationStore,
+        grab: bool,
+        recorder: Option<Arc<InputRecorder>>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut opened = Vec::new();
+
+        for device_path in device_paths {
+            log::info!("[InputHandler] Attempting to open device: {}", device_path);
+            let device = match Device::open(Path::new(device_path)) {
+                Ok(device) => device,
+                Err(e) => {
+                    log::error!("[InputHandler] Failed to open {}: {}", device_path, e);
+                    continue;
+                }
+            };
+            let name = device.name().unwrap_or("Unknown Device").to_string();
+            let id = device.input_id();
+            let key = format!(
+                "{:04x}:{:04x}@{}",
+                id.vendor(),
+                id.product(),
+                device.physical_path().unwrap_or(device_path)
+            );
+            log::info!("[InputHandler] Device opened successfully: {} ({})", name, key);
+
+            let calibration = calibrations.get(&key);
+            if calibration.is_some() {
+                log::info!("[InputHandler] Using saved calibration for {}", key);
+            }
+            if let Some(recorder) = &recorder {
+                recorder.device(opened.len(), &name, &key, &device_axes(&device), calibration);
+            }
+            opened.push(OpenedDevice {
+                identity: DeviceIdentity::of(&device, device_path),
+                device,
+                info: InputDevice::new(name, key, calibration),
+            });
+        }
+
+        if opened.is_empty() {
+            return Err(format!("none of the input devices could be opened: {}", device_paths.join(", ")).into());
+        }
+        Ok(Self { opened, grab, recorder })
+    }
+}
+
+impl InputSource for EvdevSource {
+    fn start(self, sink: InputSink) -> (Vec<InputDevice>, Vec<thread::JoinHandle<()>>) {
+        let mut devices = Vec::new();
+        let mut threads = Vec::new();
+        for (index, opened) in self.opened.into_iter().enumerate() {
+            let reader = DeviceReader {
+                identity: opened.identity,
+                index,
+                grabbed: self.grab.then(|| opened.info.grabbed.clone()),
+                recorder: self.recorder.clone(),
+                sink: sink.clone(),
+            };
+            let device = opened.device;
+            threads.push(thread::spawn(move || reader.run(device)));
+            devices.push(opened.info);
+        }
+        (devices, threads)
+    }
+}
+
+/// A fixed list of touches and raw events, played as fast as the channel takes them apart from
+/// the waits. Raw events go through a decoder per device like they would from evdev.
+#[cfg(test)]
+#[derive(Default)]
+pub struct ScriptedSource {
+    devices: Vec<(String, Vec<AxisInfo>)>,
+    steps: Vec<ScriptStep>,
+}
+
+#[cfg(test)]
+enum ScriptStep {
+    Touch(TouchInput),
+    Raw(usize, InputEvent),
+    Wait(Duration),
+}
+
+#[cfg(test)]
+impl ScriptedSource {
+    pub fn device(mut self, name: &str, axes: Vec<AxisInfo>) -> Self {
+        self.devices.push((name.to_string(), axes));
+        self
+    }
+
+    pub fn touch(mut self, touch: TouchInput) -> Self {
+        self.steps.push(ScriptStep::Touch(touch));
+        self
+    }
+
+    pub fn raw(mut self, device: usize, events: &[InputEvent]) -> Self {
+        self.steps.extend(events.iter().map(|event| ScriptStep::Raw(device, *event)));
+        self
+    }
+
+    pub fn wait(mut self, duration: Duration) -> Self {
+        self.steps.push(ScriptStep::Wait(duration));
+        self
+    }
+}
+
+#[cfg(test)]
+impl InputSource for ScriptedSource {
+    fn start(self, sink: InputSink) -> (Vec<InputDevice>, Vec<thread::JoinHandle<()>>) {
+        let devices = self
+            .devices
+            .iter()
+            .map(|(name, _)| InputDevice::new(name.clone(), format!("script@{}", name), None))
+            .collect();
+        let mut decoders: Vec<EventDecoder> = self.devices.iter().map(|(_, axes)| EventDecoder::new(axes)).collect();
+        let handle = thread::spawn(move || {
+            let mut pending = Vec::new();
+            for step in self.steps {
+                match step {
+                    ScriptStep::Touch(touch) => pending.push(touch),
+                    ScriptStep::Raw(device, event) => {
+                        let start = pending.len();
+                        decoders[device].process(&event, &mut pending);
+                        for touch in &mut pending[start..] {
+                            touch.device = device;
                         }
                     }
-                    _ => {}
+                    ScriptStep::Wait(duration) => thread::sleep(duration),
+                }
+                for touch in pending.drain(..) {
+                    if !sink.send_touch(touch) {
+                        return;
+                    }
                 }
             }
+        });
+        (devices, vec![handle])
+    }
+}
+
+pub struct InputHandler {
+    event_receiver: Receiver<TouchInput>,
+    status_receiver: Receiver<DeviceStatus>,
+    sink_closed: Arc<AtomicBool>,
+    thread_handles: Vec<thread::JoinHandle<()>>,
+    devices: Vec<InputDevice>,
+    // maps the devices without a calibration
+    transform: TouchTransform,
+}
+
+impl InputHandler {
+    pub fn new(
+        device_paths: &[String],
+        calibrations: &CalibrationStore,
+        grab: bool,
+        recorder: Option<Arc<InputRecorder>>,
+        repaint: Option<egui::Context>,
+    ) -> Result<Self, Box<dyn Error>> {
+        Ok(Self::with_source(EvdevSource::open(device_paths, calibrations, grab, recorder)?, repaint))
+    }
+
+    /// Plays a recording back in place of the devices, with its original timing.
+    pub fn replay(path: &str, repaint: Option<egui::Context>) -> Result<Self, Box<dyn Error>> {
+        Ok(Self::with_source(ReplaySource::load(path)?, repaint))
+    }
+
+    // `repaint` is woken whenever touches or status changes arrive
+    pub fn with_source(source: impl InputSource, repaint: Option<egui::Context>) -> Self {
+        let (touches, receiver) = channel();
+        let (status, status_receiver) = channel();
+        let closed = Arc::new(AtomicBool::new(false));
+        let sink = InputSink { touches, status, repaint, closed: closed.clone() };
+        let (devices, thread_handles) = source.start(sink);
+        Self {
+            event_receiver: receiver,
+            status_receiver,
+            sink_closed: closed,
+            thread_handles,
+            devices,
+            transform: TouchTransform::default(),
         }
     }
 
+    pub fn devices(&self) -> &[InputDevice] {
+        &self.devices
+    }
+
+    pub fn device_key(&self, device: usize) -> &str {
+        &self.devices[device].key
+    }
+
+    pub fn device_name(&self, device: usize) -> &str {
+        &self.devices[device].name
+    }
+
+    // every reader has stopped, e.g. a replay played to its end
+    #[cfg(test)]
+    pub fn sources_finished(&self) -> bool {
+        self.thread_handles.iter().all(|handle| handle.is_finished())
+    }
+
+    pub fn is_grabbed(&self, device: usize) -> bool {
+        self.devices[device].grabbed.load(Ordering::Relaxed)
+    }
+
+    pub fn set_calibration(&self, device: usize, affine: Option<Affine>) {
+        *self.devices[device].calibration.lock().unwrap() = affine;
+    }
+
+    pub fn touch_transform(&self) -> TouchTransform {
+        self.transform
+    }
+
+    pub fn set_touch_transform(&mut self, transform: TouchTransform) {
+        self.transform = transform;
+    }
+
+    pub fn get_status_events(&self) -> Vec<DeviceStatus> {
+        self.status_receiver.try_iter().collect()
+    }
+
     pub fn get_events(&self) -> Vec<TouchInput> {
         let mut events = Vec::new();
-        while let Ok(event) = self.event_receiver.try_recv() {
+        while let Ok(mut event) = self.event_receiver.try_recv() {
+            // every source goes through here, so recordings and scripts are mapped like the devices
+            let calibration = self.devices.get(event.device).and_then(|d| *d.calibration.lock().unwrap());
+            let mapping = calibration.unwrap_or_else(|| self.transform.affine());
+            event.screen_pos = Some(mapping.apply(event.pos));
             events.push(event);
         }
         events
@@ -192,8 +968,400 @@ impl InputHandler {
 
 impl Drop for InputHandler {
     fn drop(&mut self) {
-        if let Some(handle) = self.thread_handle.take() {
+        self.sink_closed.store(true, Ordering::Relaxed);
+        for handle in self.thread_handles.drain(..) {
             drop(handle);
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use evdev::uinput::VirtualDevice;
+    use evdev::{AbsInfo, AttributeSet, UinputAbsSetup};
+    use std::path::PathBuf;
+    use std::time::Instant;
+
+    const MAX_POSITION: i32 = 4095;
+
+    fn abs(axis: AbsoluteAxisCode, value: i32) -> InputEvent {
+        InputEvent::new(EventType::ABSOLUTE.0, axis.0, value)
+    }
+
+    fn syn() -> InputEvent {
+        InputEvent::new(EventType::SYNCHRONIZATION.0, 0, 0)
+    }
+
+    fn mt_axes() -> Vec<AxisInfo> {
+        [
+            (AbsoluteAxisCode::ABS_MT_SLOT, 9),
+            (AbsoluteAxisCode::ABS_MT_POSITION_X, MAX_POSITION),
+            (AbsoluteAxisCode::ABS_MT_POSITION_Y, MAX_POSITION),
+            (AbsoluteAxisCode::ABS_MT_TRACKING_ID, 65535),
+        ]
+        .iter()
+        .map(|&(axis, max)| AxisInfo { code: axis.0, min: 0, max })
+        .collect()
+    }
+
+    // one report each, without the SYN that ends it
+    fn touch_down(slot: i32, id: i32, x: i32, y: i32) -> Vec<InputEvent> {
+        vec![
+            abs(AbsoluteAxisCode::ABS_MT_SLOT, slot),
+            abs(AbsoluteAxisCode::ABS_MT_TRACKING_ID, id),
+            abs(AbsoluteAxisCode::ABS_MT_POSITION_X, x),
+            abs(AbsoluteAxisCode::ABS_MT_POSITION_Y, y),
+        ]
+    }
+
+    fn move_to(slot: i32, x: i32, y: i32) -> Vec<InputEvent> {
+        vec![
+            abs(AbsoluteAxisCode::ABS_MT_SLOT, slot),
+            abs(AbsoluteAxisCode::ABS_MT_POSITION_X, x),
+            abs(AbsoluteAxisCode::ABS_MT_POSITION_Y, y),
+        ]
+    }
+
+    fn lift(slot: i32) -> Vec<InputEvent> {
+        vec![abs(AbsoluteAxisCode::ABS_MT_SLOT, slot), abs(AbsoluteAxisCode::ABS_MT_TRACKING_ID, -1)]
+    }
+
+    // the reports of each gesture, every one of them is followed by a SYN
+    fn tap() -> Vec<Vec<InputEvent>> {
+        vec![touch_down(0, 1, 1000, 2000), lift(0)]
+    }
+
+    fn drag() -> Vec<Vec<InputEvent>> {
+        let mut reports = vec![touch_down(0, 2, 1000, 2000)];
+        reports.extend((1..=4).map(|step| move_to(0, 1000 + step * 200, 2000)));
+        reports.push(lift(0));
+        reports
+    }
+
+    // the panel reuses the slot for a new finger in the same report that lifts the old one
+    fn lift_off_without_syn() -> Vec<Vec<InputEvent>> {
+        let mut replace = lift(0);
+        replace.extend(touch_down(0, 4, 3000, 1000));
+        vec![touch_down(0, 3, 1000, 2000), replace, lift(0)]
+    }
+
+    fn states(touches: &[TouchInput]) -> Vec<(u32, TouchState)> {
+        touches.iter().map(|t| (t.id, t.state)).collect()
+    }
+
+    fn wait_for_events(handler: &InputHandler, count: usize) -> Vec<TouchInput> {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        let mut events = Vec::new();
+        while events.len() < count && Instant::now() < deadline {
+            events.extend(handler.get_events());
+            thread::sleep(Duration::from_millis(5));
+        }
+        events
+    }
+
+    fn scripted(reports: Vec<Vec<InputEvent>>) -> InputHandler {
+        let mut source = ScriptedSource::default().device("script", mt_axes());
+        for mut report in reports {
+            report.push(syn());
+            source = source.raw(0, &report);
+        }
+        InputHandler::with_source(source, None)
+    }
+
+    const TAP: &[(u32, TouchState)] = &[(1, TouchState::Began), (1, TouchState::Moved), (1, TouchState::Ended)];
+    const DRAG: &[(u32, TouchState)] = &[
+        (2, TouchState::Began),
+        (2, TouchState::Moved),
+        (2, TouchState::Moved),
+        (2, TouchState::Moved),
+        (2, TouchState::Moved),
+        (2, TouchState::Moved),
+        (2, TouchState::Ended),
+    ];
+    const LIFT_OFF_WITHOUT_SYN: &[(u32, TouchState)] = &[
+        (3, TouchState::Began),
+        (3, TouchState::Moved),
+        (3, TouchState::Ended),
+        (4, TouchState::Began),
+        (4, TouchState::Moved),
+        (4, TouchState::Ended),
+    ];
+
+    #[test]
+    fn scripted_gestures_decode_to_touch_streams() {
+        for (reports, expected) in [(tap(), TAP), (drag(), DRAG), (lift_off_without_syn(), LIFT_OFF_WITHOUT_SYN)] {
+            let handler = scripted(reports);
+            assert_eq!(states(&wait_for_events(&handler, expected.len())), expected);
+        }
+    }
+
+    #[test]
+    fn scripted_drag_keeps_positions_normalized() {
+        let handler = scripted(drag());
+        let events = wait_for_events(&handler, DRAG.len());
+        let first = events.first().unwrap().pos;
+        let last = events.last().unwrap().pos;
+        assert!((first.x - 1000.0 / MAX_POSITION as f32).abs() < 1e-4);
+        assert!((last.x - 1800.0 / MAX_POSITION as f32).abs() < 1e-4);
+        assert!(events.iter().all(|t| (t.pos.y - 2000.0 / MAX_POSITION as f32).abs() < 1e-4));
+    }
+
+    fn key(code: KeyCode, pressed: bool) -> InputEvent {
+        InputEvent::new(EventType::KEY.0, code.0, pressed as i32)
+    }
+
+    fn axes(ranges: &[(AbsoluteAxisCode, i32, i32)]) -> Vec<AxisInfo> {
+        ranges.iter().map(|&(axis, min, max)| AxisInfo { code: axis.0, min, max }).collect()
+    }
+
+    // straight through a decoder, each report followed by a SYN
+    fn decode(axes: &[AxisInfo], reports: Vec<Vec<InputEvent>>) -> Vec<TouchInput> {
+        let mut decoder = EventDecoder::new(axes);
+        let mut out = Vec::new();
+        for report in reports {
+            for event in report.iter().chain([&syn()]) {
+                decoder.process(event, &mut out);
+            }
+        }
+        out
+    }
+
+    fn pen_axes() -> Vec<AxisInfo> {
+        axes(&[
+            (AbsoluteAxisCode::ABS_X, 0, MAX_POSITION),
+            (AbsoluteAxisCode::ABS_Y, 0, MAX_POSITION),
+            (AbsoluteAxisCode::ABS_PRESSURE, 0, 1023),
+        ])
+    }
+
+    fn pen_at(x: i32, y: i32) -> Vec<InputEvent> {
+        vec![abs(AbsoluteAxisCode::ABS_X, x), abs(AbsoluteAxisCode::ABS_Y, y)]
+    }
+
+    #[test]
+    fn two_slots_decode_to_independent_contacts() {
+        use TouchState::*;
+        let mut both_down = touch_down(0, 10, 1000, 2000);
+        both_down.extend(touch_down(1, 20, 3000, 500));
+        let touches = decode(
+            &mt_axes(),
+            vec![both_down, move_to(1, 3100, 600), lift(0), move_to(1, 3200, 700), lift(1)],
+        );
+
+        assert_eq!(
+            states(&touches),
+            vec![(10, Began), (10, Moved), (20, Began), (20, Moved), (20, Moved), (10, Ended), (20, Moved), (20, Ended)]
+        );
+        let normalized = |x: i32, y: i32| Pos2::new(x as f32 / MAX_POSITION as f32, y as f32 / MAX_POSITION as f32);
+        let positions = |id: u32| touches.iter().filter(|t| t.id == id).map(|t| t.pos).collect::<Vec<_>>();
+        // the first finger stays put while the second one moves
+        assert!(positions(10).iter().all(|p| p.distance(normalized(1000, 2000)) < 1e-4));
+        let second = positions(20);
+        assert!(second[0].distance(normalized(3000, 500)) < 1e-4);
+        assert!(second[second.len() - 1].distance(normalized(3200, 700)) < 1e-4);
+    }
+
+    #[test]
+    fn pressure_and_contact_size_are_normalized_to_their_ranges() {
+        let mut mt = mt_axes();
+        mt.extend(axes(&[(AbsoluteAxisCode::ABS_MT_PRESSURE, 0, 255), (AbsoluteAxisCode::ABS_MT_TOUCH_MAJOR, 10, 110)]));
+        let mut down = touch_down(0, 1, 1000, 1000);
+        down.extend([abs(AbsoluteAxisCode::ABS_MT_PRESSURE, 51), abs(AbsoluteAxisCode::ABS_MT_TOUCH_MAJOR, 60)]);
+        // readings past the advertised range are clamped
+        let over = vec![abs(AbsoluteAxisCode::ABS_MT_PRESSURE, 400), abs(AbsoluteAxisCode::ABS_MT_TOUCH_MAJOR, 0)];
+        let touches = decode(&mt, vec![down, over]);
+
+        assert!((touches[0].pressure.unwrap() - 0.2).abs() < 1e-4);
+        assert!((touches[0].contact_size.unwrap() - 0.5).abs() < 1e-4);
+        let last = touches.last().unwrap();
+        assert_eq!((last.pressure, last.contact_size), (Some(1.0), Some(0.0)));
+
+        // panels without the axes leave them unset
+        let touches = decode(&mt_axes(), vec![touch_down(0, 1, 1000, 1000)]);
+        assert!(touches.iter().all(|t| t.pressure.is_none() && t.contact_size.is_none()));
+    }
+
+    #[test]
+    fn pen_tools_come_from_the_tool_buttons() {
+        use TouchState::*;
+        let stroke_with = |keys: Vec<InputEvent>| {
+            let mut down = keys;
+            down.extend(pen_at(2000, 2000));
+            down.extend([key(KeyCode::BTN_TOUCH, true), abs(AbsoluteAxisCode::ABS_PRESSURE, 512)]);
+            decode(&pen_axes(), vec![down, pen_at(2100, 2000), vec![key(KeyCode::BTN_TOUCH, false)]])
+        };
+        let tools = |touches: &[TouchInput]| {
+            touches.iter().filter(|t| matches!(t.state, Began | Moved | Ended)).map(|t| t.tool).collect::<Vec<_>>()
+        };
+
+        let pen = stroke_with(vec![key(KeyCode::BTN_TOOL_PEN, true)]);
+        assert_eq!(tools(&pen), vec![ContactTool::Pen; 4]);
+        assert!((pen[0].pressure.unwrap() - 512.0 / 1023.0).abs() < 1e-4);
+        // still in range after lifting, so it hovers again
+        assert_eq!(pen.last().map(|t| (t.state, t.tool)), Some((Hover, ContactTool::Pen)));
+
+        let rubber = stroke_with(vec![key(KeyCode::BTN_TOOL_RUBBER, true)]);
+        assert_eq!(tools(&rubber), vec![ContactTool::Eraser; 4]);
+
+        // the barrel button turns the pen into an eraser for the whole stroke
+        let stylus = stroke_with(vec![key(KeyCode::BTN_TOOL_PEN, true), key(KeyCode::BTN_STYLUS, true)]);
+        assert_eq!(tools(&stylus), vec![ContactTool::Eraser; 4]);
+
+        // without a tool button it's a finger on a single-touch panel
+        let finger = stroke_with(Vec::new());
+        assert_eq!(tools(&finger), vec![ContactTool::Finger; 4]);
+    }
+
+    #[test]
+    fn hovering_pen_draws_nothing() {
+        use TouchState::*;
+        let mut approach = vec![key(KeyCode::BTN_TOOL_PEN, true)];
+        approach.extend(pen_at(1000, 1000));
+        let touches = decode(
+            &pen_axes(),
+            vec![approach, pen_at(1500, 1000), pen_at(2000, 1200), vec![key(KeyCode::BTN_TOOL_PEN, false)]],
+        );
+
+        assert_eq!(states(&touches), vec![(PEN_CONTACT_ID, Hover), (PEN_CONTACT_ID, Hover), (PEN_CONTACT_ID, Hover), (PEN_CONTACT_ID, HoverEnded)]);
+        assert!((touches[2].pos.x - 2000.0 / MAX_POSITION as f32).abs() < 1e-4);
+    }
+
+    #[test]
+    fn scripted_touches_pass_through() {
+        let touch = TouchInput {
+            id: 7,
+            pos: Pos2::new(0.5, 0.5),
+            state: TouchState::Began,
+            pressure: None,
+            contact_size: None,
+            tool: ContactTool::Finger,
+            device: 0,
+            screen_pos: None,
+            timestamp: None,
+        };
+        let source = ScriptedSource::default()
+            .device("script", Vec::new())
+            .touch(touch)
+            .wait(Duration::from_millis(10))
+            .touch(TouchInput { state: TouchState::Ended, ..touch });
+        let handler = InputHandler::with_source(source, None);
+        assert_eq!(handler.devices().len(), 1);
+        assert_eq!(states(&wait_for_events(&handler, 2)), [(7, TouchState::Began), (7, TouchState::Ended)]);
+    }
+
+    #[test]
+    fn touches_carry_the_time_of_their_report() {
+        let mut decoder = EventDecoder::new(&mt_axes());
+        let mut out = Vec::new();
+        for event in touch_down(0, 1, 1000, 2000) {
+            decoder.process(&event, &mut out);
+        }
+        let report = InputEvent::new_now(EventType::SYNCHRONIZATION.0, 0, 0);
+        decoder.process(&report, &mut out);
+        assert_eq!(out.len(), 2);
+        assert!(out.iter().all(|t| t.timestamp == Some(report.timestamp())));
+
+        // events without a time, like the ones tests and old recordings make up, leave it unset
+        decoder.process(&abs(AbsoluteAxisCode::ABS_MT_TRACKING_ID, -1), &mut out);
+        decoder.process(&syn(), &mut out);
+        assert_eq!(out.last().map(|t| (t.state, t.timestamp)), Some((TouchState::Ended, None)));
+    }
+
+    // hands the sink back to the test instead of starting anything
+    struct KeptSink(Arc<Mutex<Option<InputSink>>>);
+
+    impl InputSource for KeptSink {
+        fn start(self, sink: InputSink) -> (Vec<InputDevice>, Vec<thread::JoinHandle<()>>) {
+            *self.0.lock().unwrap() = Some(sink);
+            (Vec::new(), Vec::new())
+        }
+    }
+
+    #[test]
+    fn lost_devices_stop_being_looked_for_once_the_handler_is_gone() {
+        let kept = Arc::new(Mutex::new(None));
+        let handler = InputHandler::with_source(KeptSink(kept.clone()), None);
+        let sink = kept.lock().unwrap().take().unwrap();
+        assert!(!sink.is_closed());
+        drop(handler);
+        assert!(sink.is_closed());
+
+        let reader = DeviceReader {
+            identity: DeviceIdentity {
+                path: "/nonexistent/event-test".to_string(),
+                name: "Gone Touchscreen".to_string(),
+                vendor: 0,
+                product: 0,
+                phys: None,
+            },
+            index: 0,
+            grabbed: None,
+            recorder: None,
+            sink,
+        };
+        let started = Instant::now();
+        assert!(reader.wait_for_device().is_none());
+        assert!(started.elapsed() < RECONNECT_POLL_INTERVAL);
+    }
+
+    // a touchscreen made up through uinput, needs write access to /dev/uinput
+    fn virtual_touchscreen() -> (VirtualDevice, PathBuf) {
+        let create = || -> std::io::Result<VirtualDevice> {
+            let mut keys = AttributeSet::<KeyCode>::new();
+            keys.insert(KeyCode::BTN_TOUCH);
+            let mut builder = VirtualDevice::builder()?.name("Praxinoscope Test Touchscreen").with_keys(&keys)?;
+            for axis in mt_axes() {
+                let setup = UinputAbsSetup::new(AbsoluteAxisCode(axis.code), AbsInfo::new(0, axis.min, axis.max, 0, 0, 0));
+                builder = builder.with_absolute_axis(&setup)?;
+            }
+            builder.build()
+        };
+        let mut device = create().expect("a uinput device can be created");
+        // udev needs a moment to create the node
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while Instant::now() < deadline {
+            let node = device
+                .enumerate_dev_nodes_blocking()
+                .ok()
+                .and_then(|mut nodes| nodes.find_map(Result::ok))
+                .filter(|path| Device::open(path).is_ok());
+            if let Some(path) = node {
+                return (device, path);
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        panic!("no device node showed up for the uinput device");
+    }
+
+    fn uinput_stream(reports: Vec<Vec<InputEvent>>, count: usize) -> Vec<(u32, TouchState)> {
+        let (mut device, path) = virtual_touchscreen();
+        let handler = InputHandler::new(&[path.display().to_string()], &CalibrationStore::default(), false, None, None)
+            .expect("the virtual device opens");
+        for report in reports {
+            // emit ends every batch with a SYN_REPORT
+            device.emit(&report).expect("uinput accepts the events");
+            thread::sleep(Duration::from_millis(10));
+        }
+        states(&wait_for_events(&handler, count))
+    }
+
+    // these go through the kernel, which CI machines and containers usually don't give access to
+    #[test]
+    #[ignore = "needs /dev/uinput, run with `cargo test -- --ignored`"]
+    fn uinput_tap() {
+        assert_eq!(uinput_stream(tap(), TAP.len()), TAP);
+    }
+
+    #[test]
+    #[ignore = "needs /dev/uinput, run with `cargo test -- --ignored`"]
+    fn uinput_drag() {
+        assert_eq!(uinput_stream(drag(), DRAG.len()), DRAG);
+    }
+
+    #[test]
+    #[ignore = "needs /dev/uinput, run with `cargo test -- --ignored`"]
+    fn uinput_lift_off_without_syn() {
+        assert_eq!(uinput_stream(lift_off_without_syn(), LIFT_OFF_WITHOUT_SYN.len()), LIFT_OFF_WITHOUT_SYN);
+    }
+}
@@ -0,0 +1,83 @@
+use eframe::egui::{self, Align2, Color32, FontId, Vec2};
+use crate::app::PaintingApp;
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime};
+
+// the overlay sums up this much history, in seconds
+const WINDOW: f64 = 1.0;
+
+/// How often frames are drawn and how old touches are by the time a frame picks them up.
+#[derive(Default)]
+pub struct LatencyStats {
+    pub show_overlay: bool,
+    // egui time of each recent frame
+    frames: VecDeque<f64>,
+    // egui time of the frame and the touch's age in seconds
+    samples: VecDeque<(f64, f64)>,
+}
+
+impl LatencyStats {
+    pub fn new(show_overlay: bool) -> Self {
+        Self { show_overlay, ..Default::default() }
+    }
+
+    pub fn frame(&mut self, now: f64) {
+        self.frames.push_back(now);
+        self.expire(now);
+    }
+
+    // the kernel stamps events with the realtime clock, so the age is measured against it too
+    pub fn touch(&mut self, now: f64, timestamp: SystemTime) {
+        let age = SystemTime::now().duration_since(timestamp).unwrap_or(Duration::ZERO);
+        self.samples.push_back((now, age.as_secs_f64()));
+    }
+
+    fn expire(&mut self, now: f64) {
+        while self.frames.front().is_some_and(|t| now - t > WINDOW) {
+            self.frames.pop_front();
+        }
+        while self.samples.front().is_some_and(|(t, _)| now - t > WINDOW) {
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn fps(&self) -> f64 {
+        match (self.frames.front(), self.frames.back()) {
+            (Some(first), Some(last)) if last > first => (self.frames.len() - 1) as f64 / (last - first),
+            _ => 0.0,
+        }
+    }
+
+    // mean and worst touch age in seconds, None without touches in the window
+    pub fn latency(&self) -> Option<(f64, f64)> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let sum: f64 = self.samples.iter().map(|(_, age)| age).sum();
+        let max = self.samples.iter().map(|(_, age)| *age).fold(0.0, f64::max);
+        Some((sum / self.samples.len() as f64, max))
+    }
+}
+
+impl PaintingApp {
+    pub fn draw_latency_overlay(&self, ctx: &egui::Context) {
+        if !self.latency.show_overlay {
+            return;
+        }
+        let latency = match self.latency.latency() {
+            Some((mean, max)) => format!("touch → frame {:.1} ms (max {:.1} ms)", mean * 1000.0, max * 1000.0),
+            None => "touch → frame: no touches".to_string(),
+        };
+        let text = format!("{:.0} fps\n{}", self.latency.fps(), latency);
+
+        let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("latency_overlay")));
+        let anchor = ctx.screen_rect().left_bottom() + Vec2::new(10.0, -10.0);
+        let galley = painter.layout_no_wrap(text, FontId::monospace(14.0), Color32::WHITE);
+        let rect = Align2::LEFT_BOTTOM.anchor_size(anchor, galley.size()).expand(6.0);
+        painter.rect_filled(rect, 4.0, Color32::from_black_alpha(180));
+        painter.galley(rect.min + Vec2::splat(6.0), galley, Color32::WHITE);
+
+        // the window keeps moving while nothing else repaints
+        ctx.request_repaint_after(Duration::from_secs_f64(WINDOW / 4.0));
+    }
+}
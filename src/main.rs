@@ -5,19 +5,54 @@ mod animation;
 mod export;
 mod utils;
 mod input;
-
-use app::PaintingApp;
+mod fill;
+mod project;
+mod history;
+mod palm;
+mod pointer;
+mod smoothing;
+mod gestures;
+mod view;
+mod recording;
+mod latency;
+mod calibration;
+mod devices;
+
+use app::{AppOptions, PaintingApp};
+use calibration::TouchTransform;
+use devices::DeviceSelector;
+use export::EXPORT_SIZE_RANGE;
 use eframe::egui;
 use clap::Parser;
 use display_info::DisplayInfo;
-use log;
 
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about)]
 struct Args {
-    #[arg(long)]
-    input: Option<String>,
+    #[arg(long, value_name = "DEVICE", help = "evdev input device, repeat for a separate pen and touch device. Skips discovery.")]
+    input: Vec<String>,
+
+    #[arg(long, value_name = "PATTERN", help = "Pick touch devices whose name contains PATTERN (case-insensitive)")]
+    input_name: Option<String>,
+
+    #[arg(long, value_name = "VENDOR:PRODUCT", value_parser = devices::parse_vendor_product, help = "Pick touch devices by USB id in hex, e.g. 04e7:0020")]
+    input_id: Option<(u16, u16)>,
+
+    #[arg(long, value_name = "PHYS", help = "Pick touch devices whose physical path contains PHYS, e.g. usb-0000:00:14.0-3")]
+    input_phys: Option<String>,
+
+    #[arg(long, help = "Grab the touch devices exclusively so the desktop doesn't also get the touches (no GNOME gestures). Panels are then driven by the app itself.")]
+    grab_input: bool,
+
+    #[arg(long, value_name = "FILE", help = "Record the raw input events and decoded touches to FILE (JSON lines) for replaying later")]
+    record_input: Option<String>,
+
+    #[arg(long, value_name = "FILE", conflicts_with = "record_input", help = "Replay touches from a recording made with --record-input instead of reading the devices")]
+    replay_input: Option<String>,
+
+    #[arg(long, help = "Print the input devices found and which would be picked, then exit")]
+    list_input_devices: bool,
 
     #[arg(long, default_value_t = 0)]
     instance: u8,
@@ -25,20 +60,94 @@ struct Args {
     #[arg(long, help = "monitor index (0 indexed)")]
     monitor: Option<u32>,
 
-    #[arg(long, help = "Invert touch input mapping for final project")]
-    invert: bool,
+    #[arg(long, value_name = "DEGREES", default_value_t = 0, value_parser = parse_rotation, help = "Clockwise rotation of uncalibrated touch panels relative to the screen: 0, 90, 180 or 270")]
+    touch_rotate: u16,
+
+    #[arg(long, help = "Mirror uncalibrated touch input left to right (after rotating)")]
+    touch_flip_x: bool,
+
+    #[arg(long, help = "Mirror uncalibrated touch input top to bottom (after rotating)")]
+    touch_flip_y: bool,
 
     #[arg(long, help = "X-offset for the window. Overrides monitor's X position if set.")]
     x_offset: Option<i32>,
+
+    #[arg(long, value_name = "FILE", help = "Project file to load on startup")]
+    load: Option<String>,
+
+    #[arg(long, value_name = "WxH", value_parser = parse_export_size, help = "Fixed export resolution, e.g. 1080x1440. Defaults to the on-screen canvas size.")]
+    export_size: Option<[u32; 2]>,
+
+    #[arg(long, help = "Start with the touchscreen calibration targets")]
+    calibrate: bool,
+
+    #[arg(long, value_name = "FILE", default_value = "calibration.json", help = "Where per-device touch calibrations are stored")]
+    calibration_file: String,
+
+    #[arg(long, help = "Show frame rate and touch-to-frame latency in a corner of the screen")]
+    latency_overlay: bool,
+
+    #[arg(long, default_value_t = 8, help = "Number of frames in a new animation (one per praxinoscope slot)")]
+    frames: usize,
+}
+
+fn parse_export_size(value: &str) -> Result<[u32; 2], String> {
+    let (w, h) = value
+        .split_once(['x', 'X'])
+        .ok_or_else(|| format!("expected WIDTHxHEIGHT, got '{}'", value))?;
+    let width: u32 = w.trim().parse().map_err(|e| format!("invalid width '{}': {}", w, e))?;
+    let height: u32 = h.trim().parse().map_err(|e| format!("invalid height '{}': {}", h, e))?;
+    if !EXPORT_SIZE_RANGE.contains(&width) || !EXPORT_SIZE_RANGE.contains(&height) {
+        return Err(format!(
+            "export size must be between {} and {} pixels on each side",
+            EXPORT_SIZE_RANGE.start(),
+            EXPORT_SIZE_RANGE.end()
+        ));
+    }
+    Ok([width, height])
+}
+
+fn parse_rotation(value: &str) -> Result<u16, String> {
+    match value.trim().parse::<u16>() {
+        Ok(degrees @ (0 | 90 | 180 | 270)) => Ok(degrees),
+        _ => Err(format!("expected 0, 90, 180 or 270, got '{}'", value)),
+    }
 }
 
 fn main() -> eframe::Result {
     env_logger::init();
 
     let args = Args::parse();
+
+    if args.list_input_devices {
+        devices::print_device_list();
+        return Ok(());
+    }
+
     let instance = args.instance;
-    let input_device_path = args.input;
-    let invert_input = args.invert;
+    let input_device_paths = if args.input.is_empty() {
+        devices::select_devices(&DeviceSelector {
+            name: args.input_name,
+            vendor_product: args.input_id,
+            phys: args.input_phys,
+        })
+    } else {
+        args.input
+    };
+    let grab_input = args.grab_input;
+    let record_input = args.record_input;
+    let replay_input = args.replay_input;
+    let touch_transform = TouchTransform {
+        rotation: args.touch_rotate,
+        flip_x: args.touch_flip_x,
+        flip_y: args.touch_flip_y,
+    };
+    let load_path = args.load;
+    let export_size = args.export_size;
+    let frame_count = args.frames;
+    let calibrate = args.calibrate;
+    let calibration_path = args.calibration_file;
+    let show_latency = args.latency_overlay;
 
     let mut viewport_builder = egui::ViewportBuilder::default();
     let mut target_position_x: f32 = 0.0;
@@ -117,7 +226,20 @@ fn main() -> eframe::Result {
         options,
         Box::new(move |cc| {
             egui_extras::install_image_loaders(&cc.egui_ctx);
-            Ok(Box::new(PaintingApp::new(input_device_path, invert_input, final_target_position)))
+            Ok(Box::new(PaintingApp::new(&cc.egui_ctx, AppOptions {
+                input_device_paths,
+                grab_input,
+                record_input,
+                replay_input,
+                touch_transform,
+                target_position: final_target_position,
+                load_path,
+                export_size,
+                frame_count,
+                calibrate,
+                calibration_path,
+                show_latency,
+            })))
         }),
     )
 }
\ No newline at end of file
@@ -1,5 +1,5 @@
 mod stroke;
 mod notification;
 
-pub use stroke::{Stroke, StrokeType};
+pub use stroke::{Stroke, StrokeType, in_paint_order};
 pub use notification::Notification;
\ No newline at end of file
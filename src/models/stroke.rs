@@ -1,23 +1,22 @@
-use eframe::egui::{Color32, Pos2};
+use eframe::egui::{Color32, Pos2, Rect};
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Debug, Default)]
 pub enum StrokeType {
+    #[default]
     Draw,
-    // Fill 
+    // points hold the filled region as (min, max) corner pairs of axis-aligned spans
+    Fill,
 }
 
-impl Default for StrokeType {
-    fn default() -> Self {
-        StrokeType::Draw
-    }
-}
-
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Debug)]
 pub struct Stroke {
     pub points: Vec<Pos2>,
     pub color: Color32,
+    // a fraction of the canvas width, like the points it scales with the canvas
     pub size: f32,
     pub stroke_type: StrokeType,
+    // per-point widths from pressure or velocity, empty when the whole stroke uses `size`
+    pub widths: Vec<f32>,
 }
 
 impl Default for Stroke {
@@ -25,8 +24,37 @@ impl Default for Stroke {
         Self {
             points: Vec::new(),
             color: Color32::BLACK,
-            size: 1.0,
+            size: 0.005,
             stroke_type: StrokeType::Draw,
+            widths: Vec::new(),
         }
     }
-}
\ No newline at end of file
+}
+
+impl Stroke {
+    pub fn is_tapered(&self) -> bool {
+        self.widths.len() == self.points.len() && !self.widths.is_empty()
+    }
+
+    pub fn width_at(&self, index: usize) -> f32 {
+        if self.is_tapered() {
+            self.widths[index]
+        } else {
+            self.size
+        }
+    }
+
+    pub fn fill_rects(&self) -> impl Iterator<Item = Rect> + '_ {
+        self.points
+            .chunks_exact(2)
+            .map(|corners| Rect::from_two_pos(corners[0], corners[1]))
+    }
+}
+
+// fills sit underneath the line work so their edges tuck under the outlines
+pub fn in_paint_order(strokes: &[Stroke]) -> impl Iterator<Item = &Stroke> {
+    strokes
+        .iter()
+        .filter(|s| s.stroke_type == StrokeType::Fill)
+        .chain(strokes.iter().filter(|s| s.stroke_type != StrokeType::Fill))
+}
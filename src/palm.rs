@@ -0,0 +1,318 @@
+use crate::input::{ContactTool, TouchInput, TouchState};
+use crate::pointer::PointerId;
+use std::collections::{HashMap, VecDeque};
+use std::time::SystemTime;
+
+// how long a rejected contact stays on the debug overlay
+pub const REJECTED_OVERLAY_SECONDS: f64 = 2.0;
+const MAX_REJECTED_MARKS: usize = 64;
+
+pub struct PalmRejectionSettings {
+    pub enabled: bool,
+    // contact size (0-1 over the device's touch/width major range) above which a touch is a palm
+    pub max_contact_size: f32,
+    // contacts lifted sooner than this are brushes of the hand rather than taps
+    pub min_duration: f64,
+    pub reject_near_pen: bool,
+    // palms tend to land right after the pen lifts, keep rejecting for this long
+    pub pen_grace: f64,
+    pub show_overlay: bool,
+}
+
+impl Default for PalmRejectionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_contact_size: 0.35,
+            min_duration: 0.04,
+            reject_near_pen: true,
+            pen_grace: 0.5,
+            show_overlay: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RejectReason {
+    TooLarge,
+    TooShort,
+    PenNearby,
+}
+
+impl RejectReason {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RejectReason::TooLarge => "too large",
+            RejectReason::TooShort => "too short",
+            RejectReason::PenNearby => "pen nearby",
+        }
+    }
+}
+
+pub struct RejectedContact {
+    pub touch: TouchInput,
+    pub reason: RejectReason,
+    pub at: f64,
+}
+
+struct TrackedContact {
+    // the latest event, what a cancel for it is built from
+    last: TouchInput,
+    began_at: f64,
+    // when the device saw it land, frames can pick up a whole tap at once
+    began_stamp: Option<SystemTime>,
+    // dropped contacts swallow the rest of their events
+    rejected: bool,
+}
+
+/// Sits between the input thread and the drawing tools and drops touches that look like a
+/// resting hand. Contacts already forwarded when they get rejected end with `Cancelled`.
+pub struct PalmFilter {
+    pub settings: PalmRejectionSettings,
+    // ids repeat across devices, so contacts are keyed like the drawing tools key them
+    contacts: HashMap<PointerId, TrackedContact>,
+    pen_near: bool,
+    pen_last_seen: f64,
+    pub rejected: VecDeque<RejectedContact>,
+}
+
+impl PalmFilter {
+    pub fn new(settings: PalmRejectionSettings) -> Self {
+        Self {
+            settings,
+            contacts: HashMap::new(),
+            pen_near: false,
+            pen_last_seen: f64::NEG_INFINITY,
+            rejected: VecDeque::new(),
+        }
+    }
+
+    pub fn filter(&mut self, events: Vec<TouchInput>, now: f64) -> Vec<TouchInput> {
+        while self.rejected.front().is_some_and(|r| now - r.at > REJECTED_OVERLAY_SECONDS) {
+            self.rejected.pop_front();
+        }
+
+        if !self.settings.enabled {
+            self.contacts.clear();
+            return events;
+        }
+
+        let mut accepted = Vec::with_capacity(events.len());
+        for event in events {
+            if event.tool != ContactTool::Finger {
+                self.track_pen(&event, now, &mut accepted);
+                accepted.push(event);
+                continue;
+            }
+
+            match event.state {
+                TouchState::Began => {
+                    let reason = if self.pen_is_near(now) {
+                        Some(RejectReason::PenNearby)
+                    } else if self.is_too_large(&event) {
+                        Some(RejectReason::TooLarge)
+                    } else {
+                        None
+                    };
+                    if let Some(reason) = reason {
+                        self.reject(&event, reason, now);
+                    } else {
+                        accepted.push(event);
+                    }
+                    self.contacts.insert(
+                        PointerId::of_touch(&event),
+                        TrackedContact { last: event, began_at: now, began_stamp: event.timestamp, rejected: reason.is_some() },
+                    );
+                }
+                TouchState::Moved => {
+                    let too_large = self.is_too_large(&event);
+                    let Some(contact) = self.contacts.get_mut(&PointerId::of_touch(&event)) else {
+                        accepted.push(event);
+                        continue;
+                    };
+                    if contact.rejected {
+                        continue;
+                    }
+                    contact.last = event;
+                    if too_large {
+                        contact.rejected = true;
+                        self.reject(&event, RejectReason::TooLarge, now);
+                        accepted.push(TouchInput { state: TouchState::Cancelled, ..event });
+                    } else {
+                        accepted.push(event);
+                    }
+                }
+                TouchState::Ended | TouchState::Cancelled => {
+                    match self.contacts.remove(&PointerId::of_touch(&event)) {
+                        Some(contact) if contact.rejected => {}
+                        Some(contact)
+                            if event.state == TouchState::Ended
+                                && held_for(&contact, &event, now) < self.settings.min_duration =>
+                        {
+                            self.reject(&event, RejectReason::TooShort, now);
+                            accepted.push(TouchInput { state: TouchState::Cancelled, ..event });
+                        }
+                        _ => accepted.push(event),
+                    }
+                }
+                TouchState::Hover | TouchState::HoverEnded => accepted.push(event),
+            }
+        }
+        accepted
+    }
+
+    fn track_pen(&mut self, event: &TouchInput, now: f64, accepted: &mut Vec<TouchInput>) {
+        self.pen_last_seen = now;
+        let was_near = self.pen_near;
+        self.pen_near = matches!(event.state, TouchState::Began | TouchState::Moved | TouchState::Hover);
+
+        // a hand already resting on the glass when the pen comes close is a palm too
+        if self.pen_near && !was_near && self.settings.reject_near_pen {
+            for contact in self.contacts.values_mut().filter(|c| !c.rejected) {
+                contact.rejected = true;
+                accepted.push(TouchInput { state: TouchState::Cancelled, timestamp: event.timestamp, ..contact.last });
+                log::debug!(
+                    "[PalmFilter] Cancelled contact {} on device {} as the pen came into range",
+                    contact.last.id, contact.last.device
+                );
+            }
+        }
+    }
+
+    fn pen_is_near(&self, now: f64) -> bool {
+        self.settings.reject_near_pen && (self.pen_near || now - self.pen_last_seen < self.settings.pen_grace)
+    }
+
+    fn is_too_large(&self, event: &TouchInput) -> bool {
+        event.contact_size.is_some_and(|size| size > self.settings.max_contact_size)
+    }
+
+    fn reject(&mut self, touch: &TouchInput, reason: RejectReason, now: f64) {
+        log::debug!("[PalmFilter] Rejected contact at ({:.2},{:.2}): {}", touch.pos.x, touch.pos.y, reason.label());
+        self.rejected.push_back(RejectedContact { touch: *touch, reason, at: now });
+        if self.rejected.len() > MAX_REJECTED_MARKS {
+            self.rejected.pop_front();
+        }
+    }
+}
+
+// device time when both ends have it, frame time otherwise
+fn held_for(contact: &TrackedContact, ended: &TouchInput, now: f64) -> f64 {
+    match (contact.began_stamp, ended.timestamp) {
+        (Some(began), Some(ended)) => ended.duration_since(began).map_or(0.0, |d| d.as_secs_f64()),
+        _ => now - contact.began_at,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::PEN_CONTACT_ID;
+    use eframe::egui::Pos2;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn finger(id: u32, state: TouchState, size: f32, millis: Option<u64>) -> TouchInput {
+        TouchInput {
+            id,
+            pos: Pos2::new(0.5, 0.5),
+            state,
+            pressure: None,
+            contact_size: Some(size),
+            tool: ContactTool::Finger,
+            device: 0,
+            screen_pos: None,
+            timestamp: millis.map(|ms| UNIX_EPOCH + Duration::from_millis(1_700_000_000_000 + ms)),
+        }
+    }
+
+    fn pen(state: TouchState) -> TouchInput {
+        TouchInput { id: PEN_CONTACT_ID, tool: ContactTool::Pen, contact_size: None, ..finger(0, state, 0.0, None) }
+    }
+
+    fn states(events: &[TouchInput]) -> Vec<(u32, TouchState)> {
+        events.iter().map(|e| (e.id, e.state)).collect()
+    }
+
+    fn reasons(filter: &PalmFilter) -> Vec<RejectReason> {
+        filter.rejected.iter().map(|r| r.reason).collect()
+    }
+
+    #[test]
+    fn large_contacts_are_dropped_or_cancelled() {
+        let mut filter = PalmFilter::new(PalmRejectionSettings::default());
+        // too large from the start, nothing of it gets through
+        let out = filter.filter(vec![finger(1, TouchState::Began, 0.6, None), finger(1, TouchState::Moved, 0.6, None)], 0.0);
+        assert!(out.is_empty());
+        let out = filter.filter(vec![finger(1, TouchState::Ended, 0.6, None)], 0.1);
+        assert!(out.is_empty());
+
+        // a fingertip that spreads into a palm ends with Cancelled
+        let out = filter.filter(vec![finger(2, TouchState::Began, 0.1, None)], 0.2);
+        assert_eq!(states(&out), vec![(2, TouchState::Began)]);
+        let out = filter.filter(vec![finger(2, TouchState::Moved, 0.5, None), finger(2, TouchState::Moved, 0.5, None)], 0.3);
+        assert_eq!(states(&out), vec![(2, TouchState::Cancelled)]);
+        assert_eq!(reasons(&filter), vec![RejectReason::TooLarge, RejectReason::TooLarge]);
+    }
+
+    #[test]
+    fn too_short_uses_device_time() {
+        let mut filter = PalmFilter::new(PalmRejectionSettings::default());
+        // a 100 ms tap picked up by a single frame is still a tap
+        let out = filter.filter(vec![finger(1, TouchState::Began, 0.1, Some(0)), finger(1, TouchState::Ended, 0.1, Some(100))], 1.0);
+        assert_eq!(states(&out), vec![(1, TouchState::Began), (1, TouchState::Ended)]);
+
+        // a 10 ms brush spread over two frames is not
+        let out = filter.filter(vec![finger(2, TouchState::Began, 0.1, Some(1000))], 2.0);
+        assert_eq!(states(&out), vec![(2, TouchState::Began)]);
+        let out = filter.filter(vec![finger(2, TouchState::Ended, 0.1, Some(1010))], 2.5);
+        assert_eq!(states(&out), vec![(2, TouchState::Cancelled)]);
+        assert_eq!(reasons(&filter), vec![RejectReason::TooShort]);
+    }
+
+    #[test]
+    fn too_short_falls_back_to_frame_time() {
+        let mut filter = PalmFilter::new(PalmRejectionSettings::default());
+        let out = filter.filter(vec![finger(1, TouchState::Began, 0.1, None), finger(1, TouchState::Ended, 0.1, None)], 1.0);
+        assert_eq!(states(&out), vec![(1, TouchState::Began), (1, TouchState::Cancelled)]);
+
+        filter.filter(vec![finger(2, TouchState::Began, 0.1, None)], 2.0);
+        let out = filter.filter(vec![finger(2, TouchState::Ended, 0.1, None)], 2.1);
+        assert_eq!(states(&out), vec![(2, TouchState::Ended)]);
+    }
+
+    #[test]
+    fn fingers_near_the_pen_are_rejected() {
+        let mut filter = PalmFilter::new(PalmRejectionSettings::default());
+        // resting before the pen arrives, cancelled once it does
+        filter.filter(vec![finger(1, TouchState::Began, 0.1, None)], 0.0);
+        let out = filter.filter(vec![pen(TouchState::Hover)], 0.1);
+        assert_eq!(states(&out), vec![(1, TouchState::Cancelled), (PEN_CONTACT_ID, TouchState::Hover)]);
+        assert!(filter.filter(vec![finger(1, TouchState::Moved, 0.1, None)], 0.2).is_empty());
+
+        // landing while the pen is close, or just after it left
+        assert!(filter.filter(vec![finger(2, TouchState::Began, 0.1, None)], 0.3).is_empty());
+        filter.filter(vec![pen(TouchState::HoverEnded)], 0.4);
+        assert!(filter.filter(vec![finger(3, TouchState::Began, 0.1, None)], 0.6).is_empty());
+        assert_eq!(reasons(&filter), vec![RejectReason::PenNearby, RejectReason::PenNearby]);
+
+        // once the grace period is over fingers draw again
+        let out = filter.filter(vec![finger(4, TouchState::Began, 0.1, None)], 1.0);
+        assert_eq!(states(&out), vec![(4, TouchState::Began)]);
+    }
+
+    #[test]
+    fn contacts_are_told_apart_by_device() {
+        let mut filter = PalmFilter::new(PalmRejectionSettings::default());
+        let on_second = |touch: TouchInput| TouchInput { device: 1, ..touch };
+        // both devices start numbering their contacts at the same id
+        filter.filter(vec![finger(1, TouchState::Began, 0.6, None)], 0.0);
+        let out = filter.filter(vec![on_second(finger(1, TouchState::Began, 0.1, None))], 0.1);
+        assert_eq!(states(&out), vec![(1, TouchState::Began)]);
+
+        // the palm on the first device doesn't swallow the finger on the second
+        let out = filter.filter(vec![finger(1, TouchState::Moved, 0.6, None), on_second(finger(1, TouchState::Moved, 0.1, None))], 0.2);
+        assert_eq!(out.iter().map(|e| (e.device, e.state)).collect::<Vec<_>>(), vec![(1, TouchState::Moved)]);
+        let out = filter.filter(vec![on_second(finger(1, TouchState::Ended, 0.1, None)), finger(1, TouchState::Ended, 0.6, None)], 0.3);
+        assert_eq!(out.iter().map(|e| (e.device, e.state)).collect::<Vec<_>>(), vec![(1, TouchState::Ended)]);
+    }
+}
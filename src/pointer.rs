@@ -0,0 +1,270 @@
+use eframe::egui::{self, Pos2, Rect};
+use crate::app::PaintingApp;
+use crate::input::{ContactTool, TouchInput, TouchState};
+use crate::ui::{handle_frame_strip_touch, touch_to_screen};
+use std::collections::HashSet;
+
+// the egui mouse has no ids of its own
+const MOUSE_POINTER_ID: u64 = 0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PointerSource {
+    Mouse,
+    // touches the windowing system hands to egui
+    Touch,
+    // contacts read straight from an evdev device, by index into InputHandler::devices
+    Evdev(usize),
+}
+
+// ids are only unique within a source, evdev ids even only within one device
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct PointerId {
+    pub source: PointerSource,
+    pub id: u64,
+}
+
+impl PointerId {
+    pub fn of_touch(touch: &TouchInput) -> Self {
+        Self { source: PointerSource::Evdev(touch.device), id: touch.id as u64 }
+    }
+}
+
+/// One step of a mouse, touch or pen contact in screen space, what the drawing tools work on.
+#[derive(Clone, Copy, Debug)]
+pub struct PointerEvent {
+    pub id: PointerId,
+    pub pos: Pos2,
+    pub state: TouchState,
+    // 0-1, None when the source doesn't report it
+    pub pressure: Option<f32>,
+    pub contact_size: Option<f32>,
+    pub tool: ContactTool,
+}
+
+impl PointerEvent {
+    pub fn from_touch(touch: &TouchInput, pos: Pos2) -> Self {
+        Self {
+            id: PointerId::of_touch(touch),
+            pos,
+            state: touch.state,
+            pressure: touch.pressure,
+            contact_size: touch.contact_size,
+            tool: touch.tool,
+        }
+    }
+
+    fn egui(source: PointerSource, id: u64, pos: Pos2, state: TouchState, pressure: Option<f32>) -> Self {
+        Self {
+            id: PointerId { source, id },
+            pos,
+            state,
+            pressure,
+            contact_size: None,
+            tool: ContactTool::Finger,
+        }
+    }
+}
+
+/// Follows egui's mouse and touch events across frames and turns them into pointer events.
+#[derive(Default)]
+pub struct EguiPointerTracker {
+    mouse_down: bool,
+    mouse_pos: Option<Pos2>,
+    touches: HashSet<u64>,
+    // the touch egui-winit is currently playing the mouse with, like its own `pointer_touch_id`
+    emulating_touch: Option<u64>,
+}
+
+impl EguiPointerTracker {
+    // contacts only start on the canvas, a press on the panels belongs to egui's own widgets.
+    // With `skip_touches` the touches, and the mouse emulated from them, are already read from evdev
+    pub fn collect(&mut self, events: &[egui::Event], canvas_rect: Rect, skip_touches: bool, out: &mut Vec<PointerEvent>) {
+        // pointer events egui-winit pushed right after a touch to emulate the mouse with it
+        let mut emulated = 0;
+
+        for event in events {
+            match *event {
+                egui::Event::Touch { id, phase, pos, force, .. } => {
+                    emulated = self.emulated_pointer_events(id.0, phase);
+                    if skip_touches {
+                        continue;
+                    }
+                    let state = match phase {
+                        egui::TouchPhase::Start if canvas_rect.contains(pos) => {
+                            self.touches.insert(id.0);
+                            TouchState::Began
+                        }
+                        egui::TouchPhase::Move if self.touches.contains(&id.0) => TouchState::Moved,
+                        egui::TouchPhase::End if self.touches.remove(&id.0) => TouchState::Ended,
+                        egui::TouchPhase::Cancel if self.touches.remove(&id.0) => TouchState::Cancelled,
+                        _ => continue,
+                    };
+                    out.push(PointerEvent::egui(PointerSource::Touch, id.0, pos, state, force));
+                }
+                egui::Event::PointerMoved(_) | egui::Event::PointerButton { .. } | egui::Event::PointerGone
+                    if emulated > 0 =>
+                {
+                    emulated -= 1;
+                }
+                egui::Event::PointerMoved(pos) => {
+                    self.mouse_pos = Some(pos);
+                    let state = if self.mouse_down { TouchState::Moved } else { TouchState::Hover };
+                    out.push(PointerEvent::egui(PointerSource::Mouse, MOUSE_POINTER_ID, pos, state, None));
+                }
+                egui::Event::PointerButton { pos, button: egui::PointerButton::Primary, pressed, .. } => {
+                    let state = match (pressed, self.mouse_down) {
+                        (true, false) if canvas_rect.contains(pos) => TouchState::Began,
+                        (false, true) => TouchState::Ended,
+                        _ => continue,
+                    };
+                    self.mouse_down = pressed;
+                    out.push(PointerEvent::egui(PointerSource::Mouse, MOUSE_POINTER_ID, pos, state, None));
+                }
+                // a held button keeps drawing when the mouse comes back, like egui's own drags
+                egui::Event::PointerGone if !self.mouse_down => {
+                    if let Some(pos) = self.mouse_pos.take() {
+                        out.push(PointerEvent::egui(PointerSource::Mouse, MOUSE_POINTER_ID, pos, TouchState::HoverEnded, None));
+                    }
+                }
+                _ => emulated = 0,
+            }
+        }
+    }
+
+    // mirrors egui-winit's `on_touch`: the first finger moves the pointer and presses the button
+    fn emulated_pointer_events(&mut self, id: u64, phase: egui::TouchPhase) -> usize {
+        if self.emulating_touch.is_some_and(|touch| touch != id) {
+            return 0;
+        }
+        match phase {
+            egui::TouchPhase::Start => {
+                self.emulating_touch = Some(id);
+                2
+            }
+            egui::TouchPhase::Move => 1,
+            egui::TouchPhase::End => {
+                self.emulating_touch = None;
+                2
+            }
+            egui::TouchPhase::Cancel => {
+                self.emulating_touch = None;
+                1
+            }
+        }
+    }
+}
+
+impl PaintingApp {
+    // a device we read but didn't grab also reaches the window, its touches would draw twice.
+    // egui doesn't say which device a touch came from, so then all of egui's touches are left out
+    pub fn egui_touches_from_evdev(&self) -> bool {
+        self.input_handler
+            .as_ref()
+            .is_some_and(|handler| (0..handler.devices().len()).any(|i| !handler.is_grabbed(i)))
+    }
+
+    /// Every source's contacts for this frame, in screen space. Evdev touches meant for the
+    /// frame strip are handled on the way and left out.
+    pub fn collect_pointer_events(&mut self, ctx: &egui::Context, canvas_rect: Rect) -> Vec<PointerEvent> {
+        let screen_rect = ctx.screen_rect();
+        let now = ctx.input(|i| i.time);
+        let mut pointer_events = Vec::new();
+
+        for touch in std::mem::take(&mut self.pending_touches) {
+            let pos = touch_to_screen(screen_rect, &touch);
+            if handle_frame_strip_touch(self, PointerId::of_touch(&touch), touch.state, pos, now) {
+                continue;
+            }
+            pointer_events.push(PointerEvent::from_touch(&touch, pos));
+        }
+
+        if self.calibration.is_none() {
+            let events = ctx.input(|i| i.events.clone());
+            let skip_touches = self.egui_touches_from_evdev();
+            self.egui_pointers.collect(&events, canvas_rect, skip_touches, &mut pointer_events);
+        }
+        pointer_events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn touch(id: u64, phase: egui::TouchPhase, pos: Pos2) -> egui::Event {
+        egui::Event::Touch { device_id: egui::TouchDeviceId(1), id: egui::TouchId(id), phase, pos, force: None }
+    }
+
+    fn button(pos: Pos2, pressed: bool) -> egui::Event {
+        egui::Event::PointerButton { pos, button: egui::PointerButton::Primary, pressed, modifiers: Default::default() }
+    }
+
+    // a finger as egui-winit reports it, each touch followed by the mouse it emulates, and a real
+    // mouse click in between
+    fn finger_and_mouse() -> Vec<egui::Event> {
+        let finger = Pos2::new(100.0, 100.0);
+        let mouse = Pos2::new(300.0, 300.0);
+        vec![
+            touch(5, egui::TouchPhase::Start, finger),
+            egui::Event::PointerMoved(finger),
+            button(finger, true),
+            egui::Event::PointerMoved(mouse),
+            button(mouse, true),
+            touch(5, egui::TouchPhase::Move, finger),
+            egui::Event::PointerMoved(finger),
+            button(mouse, false),
+            touch(5, egui::TouchPhase::End, finger),
+            button(finger, false),
+            egui::Event::PointerGone,
+        ]
+    }
+
+    fn collect(skip_touches: bool) -> Vec<(PointerSource, TouchState)> {
+        let canvas = Rect::from_min_size(Pos2::ZERO, egui::vec2(500.0, 500.0));
+        let mut out = Vec::new();
+        EguiPointerTracker::default().collect(&finger_and_mouse(), canvas, skip_touches, &mut out);
+        out.iter().map(|e| (e.id.source, e.state)).collect()
+    }
+
+    #[test]
+    fn mouse_emulated_from_touches_is_left_out() {
+        use TouchState::*;
+        assert_eq!(
+            collect(false),
+            vec![
+                (PointerSource::Touch, Began),
+                (PointerSource::Mouse, Hover),
+                (PointerSource::Mouse, Began),
+                (PointerSource::Touch, Moved),
+                (PointerSource::Mouse, Ended),
+                (PointerSource::Touch, Ended),
+            ]
+        );
+    }
+
+    #[test]
+    fn skipped_touches_keep_the_real_mouse() {
+        use TouchState::*;
+        assert_eq!(collect(true), vec![(PointerSource::Mouse, Hover), (PointerSource::Mouse, Began), (PointerSource::Mouse, Ended)]);
+    }
+
+    #[test]
+    fn frame_strip_drags_belong_to_one_device() {
+        use crate::app::{AppOptions, PaintingApp};
+        let ctx = egui::Context::default();
+        let mut app = PaintingApp::new(&ctx, AppOptions::headless());
+        let thumb = Rect::from_min_size(Pos2::new(0.0, 0.0), egui::vec2(80.0, 60.0));
+        app.frame_thumb_rects = vec![thumb];
+        let first = PointerId { source: PointerSource::Evdev(0), id: 3 };
+        let second = PointerId { source: PointerSource::Evdev(1), id: 3 };
+
+        assert!(handle_frame_strip_touch(&mut app, first, TouchState::Began, thumb.center(), 0.0));
+        // the same tracking id on another panel is a different finger
+        assert!(!handle_frame_strip_touch(&mut app, second, TouchState::Moved, Pos2::new(300.0, 300.0), 0.1));
+        assert!(!handle_frame_strip_touch(&mut app, second, TouchState::Ended, Pos2::new(300.0, 300.0), 0.2));
+        assert!(app.frame_drag.as_ref().is_some_and(|drag| drag.touch_id == Some(first) && drag.pos == thumb.center()));
+
+        assert!(handle_frame_strip_touch(&mut app, first, TouchState::Ended, thumb.center(), 0.3));
+        assert!(app.frame_drag.is_none());
+    }
+}
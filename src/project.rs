@@ -0,0 +1,267 @@
+use crate::app::{PaintingApp, MAX_FRAMES};
+use crate::models::{Stroke, StrokeType};
+use eframe::egui::{Color32, Pos2};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::Path;
+
+pub const PROJECT_FORMAT: &str = "praxinoscope-project";
+// version 2 stores stroke sizes as fractions of the canvas width instead of screen pixels
+pub const PROJECT_VERSION: u32 = 2;
+// curve fitting can overshoot the canvas edge a little, anything further out is a broken file
+const POINT_MARGIN: f32 = 0.1;
+
+#[derive(Serialize, Deserialize)]
+pub struct ProjectFile {
+    pub format: String,
+    pub version: u32,
+    pub aspect_ratio: f32,
+    pub fps: f32,
+    pub onion_skin: OnionSkinSettings,
+    pub frames: Vec<Vec<StrokeRecord>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OnionSkinSettings {
+    pub enabled: bool,
+    pub opacity: f32,
+    pub prev_color: [u8; 4],
+    pub next_color: [u8; 4],
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StrokeKind {
+    Draw,
+    Fill,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct StrokeRecord {
+    pub kind: StrokeKind,
+    pub color: [u8; 4],
+    // fraction of the canvas width
+    pub size: f32,
+    // normalized to the canvas rect, (0,0) top left and (1,1) bottom right
+    pub points: Vec<[f32; 2]>,
+    // one width per point for pressure strokes, omitted for uniform ones
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub widths: Vec<f32>,
+}
+
+impl StrokeRecord {
+    fn from_stroke(stroke: &Stroke) -> Self {
+        Self {
+            kind: match stroke.stroke_type {
+                StrokeType::Draw => StrokeKind::Draw,
+                StrokeType::Fill => StrokeKind::Fill,
+            },
+            color: color_to_array(stroke.color),
+            size: stroke.size,
+            points: stroke.points.iter().map(|p| [p.x, p.y]).collect(),
+            widths: stroke.widths.clone(),
+        }
+    }
+
+    fn to_stroke(&self) -> Stroke {
+        Stroke {
+            points: self.points.iter().map(|[x, y]| Pos2::new(*x, *y)).collect(),
+            color: array_to_color(self.color),
+            size: self.size,
+            stroke_type: match self.kind {
+                StrokeKind::Draw => StrokeType::Draw,
+                StrokeKind::Fill => StrokeType::Fill,
+            },
+            widths: self.widths.clone(),
+        }
+    }
+}
+
+fn color_to_array(color: Color32) -> [u8; 4] {
+    color.to_srgba_unmultiplied()
+}
+
+fn array_to_color([r, g, b, a]: [u8; 4]) -> Color32 {
+    Color32::from_rgba_unmultiplied(r, g, b, a)
+}
+
+impl PaintingApp {
+    pub fn to_project_file(&self) -> ProjectFile {
+        ProjectFile {
+            format: PROJECT_FORMAT.to_string(),
+            version: PROJECT_VERSION,
+            aspect_ratio: self.canvas_aspect_ratio,
+            fps: self.animation_speed,
+            onion_skin: OnionSkinSettings {
+                enabled: self.show_onion_skin,
+                opacity: self.onion_skin_opacity,
+                prev_color: color_to_array(self.prev_onion_color),
+                next_color: color_to_array(self.next_onion_color),
+            },
+            frames: self
+                .frames
+                .iter()
+                .map(|strokes| strokes.iter().map(StrokeRecord::from_stroke).collect())
+                .collect(),
+        }
+    }
+
+    pub fn apply_project_file(&mut self, project: ProjectFile) -> Result<(), Box<dyn Error>> {
+        if project.format != PROJECT_FORMAT {
+            return Err(format!("not a project file (format '{}')", project.format).into());
+        }
+        if project.version > PROJECT_VERSION {
+            return Err(format!(
+                "project version {} is newer than the supported version {}",
+                project.version, PROJECT_VERSION
+            )
+            .into());
+        }
+        if project.version < PROJECT_VERSION {
+            return Err(format!(
+                "project version {} stores stroke sizes in screen pixels, only version {} can be loaded",
+                project.version, PROJECT_VERSION
+            )
+            .into());
+        }
+        if project.frames.is_empty() {
+            return Err("project has no frames".into());
+        }
+        if project.frames.len() > MAX_FRAMES {
+            return Err(format!("project has {} frames, the maximum is {}", project.frames.len(), MAX_FRAMES).into());
+        }
+        if !project.aspect_ratio.is_finite() || project.aspect_ratio <= 0.0 {
+            return Err(format!("invalid aspect ratio {}", project.aspect_ratio).into());
+        }
+        let in_range = |v: f32| (-POINT_MARGIN..=1.0 + POINT_MARGIN).contains(&v);
+        for (frame_index, strokes) in project.frames.iter().enumerate() {
+            if let Some([x, y]) = strokes.iter().flat_map(|s| &s.points).find(|[x, y]| !in_range(*x) || !in_range(*y)) {
+                return Err(format!("frame {} has a point outside the canvas ({}, {})", frame_index + 1, x, y).into());
+            }
+        }
+
+        self.canvas_aspect_ratio = project.aspect_ratio;
+        self.animation_speed = project.fps.clamp(1.0, 24.0);
+        self.show_onion_skin = project.onion_skin.enabled;
+        self.onion_skin_opacity = project.onion_skin.opacity;
+        self.prev_onion_color = array_to_color(project.onion_skin.prev_color);
+        self.next_onion_color = array_to_color(project.onion_skin.next_color);
+
+        self.frames = project
+            .frames
+            .iter()
+            .map(|strokes| strokes.iter().map(StrokeRecord::to_stroke).collect())
+            .collect();
+        self.current_frame = 0;
+        self.active_touches.clear();
+        self.history.clear();
+
+        Ok(())
+    }
+
+    pub fn save_project(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let project = self.to_project_file();
+        let json = serde_json::to_string_pretty(&project)?;
+
+        // write next to the target first so a crash mid-save never leaves a truncated project
+        let tmp_path = format!("{}.tmp", path);
+        std::fs::write(&tmp_path, json)?;
+        std::fs::rename(&tmp_path, path)?;
+
+        log::info!("[Project] Saved {} frames to {}", self.frames.len(), path);
+        Ok(())
+    }
+
+    pub fn load_project(&mut self, path: &str) -> Result<(), Box<dyn Error>> {
+        let json = std::fs::read_to_string(Path::new(path))?;
+        let project: ProjectFile = serde_json::from_str(&json)?;
+        self.apply_project_file(project)?;
+
+        log::info!("[Project] Loaded {} frames from {}", self.frames.len(), path);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::AppOptions;
+    use eframe::egui;
+
+    fn app() -> PaintingApp {
+        PaintingApp::new(&egui::Context::default(), AppOptions::headless())
+    }
+
+    fn drawn_app() -> PaintingApp {
+        let mut app = app();
+        app.frames[0].push(Stroke {
+            points: vec![Pos2::new(0.1, 0.2), Pos2::new(0.5, 0.6), Pos2::new(0.9, 0.3)],
+            color: Color32::from_rgba_unmultiplied(10, 20, 30, 200),
+            size: 0.01,
+            stroke_type: StrokeType::Draw,
+            widths: vec![0.005, 0.02, 0.01],
+        });
+        app.frames[2].push(Stroke {
+            points: vec![Pos2::new(0.0, 0.0), Pos2::new(1.0, 0.5)],
+            color: Color32::from_rgb(0, 128, 255),
+            size: 0.0,
+            stroke_type: StrokeType::Fill,
+            widths: Vec::new(),
+        });
+        app.animation_speed = 12.0;
+        app.canvas_aspect_ratio = 16.0 / 9.0;
+        app.show_onion_skin = false;
+        app.onion_skin_opacity = 0.55;
+        app.prev_onion_color = Color32::from_rgb(1, 2, 3);
+        app.next_onion_color = Color32::from_rgba_unmultiplied(4, 5, 6, 128);
+        app
+    }
+
+    #[test]
+    fn saved_projects_load_back_unchanged() {
+        let path = std::env::temp_dir().join(format!("praxinoscope-project-test-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let saved = drawn_app();
+        saved.save_project(path).unwrap();
+
+        let mut loaded = app();
+        loaded.load_project(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(loaded.frames, saved.frames);
+        assert_eq!(loaded.animation_speed, 12.0);
+        assert_eq!(loaded.canvas_aspect_ratio, saved.canvas_aspect_ratio);
+        assert!(!loaded.show_onion_skin);
+        assert_eq!(loaded.onion_skin_opacity, 0.55);
+        assert_eq!(loaded.prev_onion_color, saved.prev_onion_color);
+        assert_eq!(loaded.next_onion_color, saved.next_onion_color);
+    }
+
+    // the project is rejected with a message mentioning `reason`, and the open one stays as it was
+    fn assert_rejected(change: impl FnOnce(&mut ProjectFile), reason: &str) {
+        let mut project = drawn_app().to_project_file();
+        change(&mut project);
+        let mut app = app();
+        let error = app.apply_project_file(project).unwrap_err().to_string();
+        assert!(error.contains(reason), "{}", error);
+        assert!(app.frames.iter().all(|f| f.is_empty()));
+        assert_eq!(app.animation_speed, 10.0);
+    }
+
+    #[test]
+    fn other_formats_are_rejected() {
+        assert_rejected(|p| p.format = "something-else".to_string(), "not a project file");
+    }
+
+    #[test]
+    fn unsupported_versions_are_rejected() {
+        assert_rejected(|p| p.version = PROJECT_VERSION + 1, "newer than the supported version");
+        assert_rejected(|p| p.version = 1, "screen pixels");
+    }
+
+    #[test]
+    fn points_off_the_canvas_are_rejected() {
+        assert_rejected(|p| p.frames[2][0].points[1] = [1.0, 3.0], "frame 3 has a point outside the canvas");
+        assert_rejected(|p| p.frames[0][0].points[0][0] = f32::NAN, "frame 1 has a point outside the canvas");
+    }
+}
@@ -0,0 +1,355 @@
+use crate::calibration::Affine;
+use crate::input::{AxisInfo, ContactTool, EventDecoder, InputDevice, InputSink, InputSource, TouchInput, TouchState};
+use eframe::egui::Pos2;
+use evdev::InputEvent;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub const RECORDING_FORMAT: &str = "praxinoscope-input";
+pub const RECORDING_VERSION: u32 = 1;
+
+// one JSON object per line, so a recording cut short by a crash or unplug still loads
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RecordEntry {
+    Header { format: String, version: u32 },
+    Device { index: usize, name: String, key: String, axes: Vec<AxisInfo>, calibration: Option<Affine> },
+    // `t` is seconds since the recording started, `time` the kernel timestamp in seconds since the epoch
+    Raw { t: f64, device: usize, time: f64, event_type: u16, code: u16, value: i32 },
+    Touch { t: f64, touch: TouchRecord },
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct TouchRecord {
+    pub id: u32,
+    pub pos: [f32; 2],
+    pub state: TouchState,
+    pub pressure: Option<f32>,
+    pub contact_size: Option<f32>,
+    pub tool: ContactTool,
+    pub device: usize,
+    pub screen_pos: Option<[f32; 2]>,
+    // seconds since the epoch, like the raw events' `time`
+    #[serde(default)]
+    pub timestamp: Option<f64>,
+}
+
+impl TouchRecord {
+    fn from_touch(touch: &TouchInput) -> Self {
+        Self {
+            id: touch.id,
+            pos: [touch.pos.x, touch.pos.y],
+            state: touch.state,
+            pressure: touch.pressure,
+            contact_size: touch.contact_size,
+            tool: touch.tool,
+            device: touch.device,
+            screen_pos: touch.screen_pos.map(|p| [p.x, p.y]),
+            timestamp: touch.timestamp.map(epoch_seconds),
+        }
+    }
+
+    fn to_touch(self) -> TouchInput {
+        TouchInput {
+            id: self.id,
+            pos: Pos2::new(self.pos[0], self.pos[1]),
+            state: self.state,
+            pressure: self.pressure,
+            contact_size: self.contact_size,
+            tool: self.tool,
+            device: self.device,
+            screen_pos: self.screen_pos.map(|[x, y]| Pos2::new(x, y)),
+            timestamp: self.timestamp.and_then(from_epoch_seconds),
+        }
+    }
+}
+
+fn epoch_seconds(time: SystemTime) -> f64 {
+    time.duration_since(UNIX_EPOCH).map_or(0.0, |d| d.as_secs_f64())
+}
+
+// zero is what events without a time carry
+fn from_epoch_seconds(seconds: f64) -> Option<SystemTime> {
+    (seconds > 0.0).then(|| UNIX_EPOCH + Duration::from_secs_f64(seconds))
+}
+
+/// Writes what the reader threads see to a file, shared by all of them.
+pub struct InputRecorder {
+    writer: Mutex<BufWriter<File>>,
+    started: Instant,
+    // a full disk shouldn't flood the log with one error per event
+    failed: AtomicBool,
+}
+
+impl InputRecorder {
+    pub fn create(path: &str) -> Result<Self, Box<dyn Error>> {
+        let recorder = Self {
+            writer: Mutex::new(BufWriter::new(File::create(path)?)),
+            started: Instant::now(),
+            failed: AtomicBool::new(false),
+        };
+        recorder.write(&RecordEntry::Header { format: RECORDING_FORMAT.to_string(), version: RECORDING_VERSION });
+        log::info!("[Recording] Recording input to {}", path);
+        Ok(recorder)
+    }
+
+    pub fn device(&self, index: usize, name: &str, key: &str, axes: &[AxisInfo], calibration: Option<Affine>) {
+        self.write(&RecordEntry::Device {
+            index,
+            name: name.to_string(),
+            key: key.to_string(),
+            axes: axes.to_vec(),
+            calibration,
+        });
+    }
+
+    pub fn raw(&self, device: usize, event: &InputEvent) {
+        self.write(&RecordEntry::Raw {
+            t: self.started.elapsed().as_secs_f64(),
+            device,
+            time: epoch_seconds(event.timestamp()),
+            event_type: event.event_type().0,
+            code: event.code(),
+            value: event.value(),
+        });
+    }
+
+    pub fn touch(&self, touch: &TouchInput) {
+        self.write(&RecordEntry::Touch { t: self.started.elapsed().as_secs_f64(), touch: TouchRecord::from_touch(touch) });
+    }
+
+    fn write(&self, entry: &RecordEntry) {
+        let result = (|| -> Result<(), Box<dyn Error>> {
+            let line = serde_json::to_string(entry)?;
+            let mut writer = self.writer.lock().unwrap();
+            writeln!(writer, "{}", line)?;
+            // flushed per entry so the file is complete whenever the app gets killed
+            writer.flush()?;
+            Ok(())
+        })();
+        if let Err(e) = result {
+            if !self.failed.swap(true, Ordering::Relaxed) {
+                log::error!("[Recording] Failed to write input recording: {}. Further errors are not logged.", e);
+            }
+        }
+    }
+}
+
+pub struct RecordedDevice {
+    pub name: String,
+    pub key: String,
+    pub axes: Vec<AxisInfo>,
+    pub calibration: Option<Affine>,
+}
+
+pub struct RawRecord {
+    pub t: f64,
+    pub device: usize,
+    pub time: Option<SystemTime>,
+    pub event: InputEvent,
+}
+
+pub struct Recording {
+    pub devices: Vec<RecordedDevice>,
+    pub raw_events: Vec<RawRecord>,
+    // what the decoder produced while recording, to compare a replay against
+    pub touches: Vec<TouchInput>,
+}
+
+impl Recording {
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut devices: Vec<Option<RecordedDevice>> = Vec::new();
+        let mut raw_events = Vec::new();
+        let mut touches = Vec::new();
+
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: RecordEntry =
+                serde_json::from_str(&line).map_err(|e| format!("{} line {}: {}", path, number + 1, e))?;
+            match entry {
+                RecordEntry::Header { format, version } => {
+                    if format != RECORDING_FORMAT {
+                        return Err(format!("{} is not an input recording (format '{}')", path, format).into());
+                    }
+                    if version > RECORDING_VERSION {
+                        return Err(format!("{} was recorded by a newer version ({})", path, version).into());
+                    }
+                }
+                RecordEntry::Device { index, name, key, axes, calibration } => {
+                    if devices.len() <= index {
+                        devices.resize_with(index + 1, || None);
+                    }
+                    devices[index] = Some(RecordedDevice { name, key, axes, calibration });
+                }
+                RecordEntry::Raw { t, device, time, event_type, code, value } => {
+                    if devices.get(device).is_none_or(|d| d.is_none()) {
+                        return Err(format!("{} line {}: event for unknown device {}", path, number + 1, device).into());
+                    }
+                    raw_events.push(RawRecord {
+                        t,
+                        device,
+                        time: from_epoch_seconds(time),
+                        event: InputEvent::new(event_type, code, value),
+                    });
+                }
+                RecordEntry::Touch { touch, .. } => touches.push(touch.to_touch()),
+            }
+        }
+
+        let devices = devices
+            .into_iter()
+            .enumerate()
+            .map(|(index, d)| d.ok_or_else(|| format!("{} has no header for device {}", path, index)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { devices, raw_events, touches })
+    }
+
+    /// Runs the raw events through fresh decoders, the way the reader threads do, handing each
+    /// touch to `emit` with the time its event came in. Stops early when `emit` returns false.
+    pub fn decode(&self, mut emit: impl FnMut(f64, TouchInput) -> bool) {
+        let mut decoders: Vec<EventDecoder> = self.devices.iter().map(|d| EventDecoder::new(&d.axes)).collect();
+        let mut pending = Vec::new();
+
+        for raw in &self.raw_events {
+            decoders[raw.device].process(&raw.event, &mut pending);
+            for mut touch in pending.drain(..) {
+                touch.device = raw.device;
+                // rebuilt events have no time of their own
+                touch.timestamp = raw.time;
+                if !emit(raw.t, touch) {
+                    return;
+                }
+            }
+        }
+    }
+
+    pub fn decoded_touches(&self) -> Vec<TouchInput> {
+        let mut touches = Vec::new();
+        self.decode(|_, touch| {
+            touches.push(touch);
+            true
+        });
+        touches
+    }
+}
+
+/// A recording played back in place of the devices, with its original timing.
+pub struct ReplaySource {
+    recording: Recording,
+}
+
+impl ReplaySource {
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let recording = Recording::load(path)?;
+        log::info!(
+            "[Recording] Replaying {} ({} devices, {} raw events)",
+            path, recording.devices.len(), recording.raw_events.len()
+        );
+        // the decoder changed since the recording was made, what gets drawn may differ too
+        if recording.decoded_touches() != recording.touches {
+            log::warn!("[Recording] Replayed touches differ from the ones recorded in {}", path);
+        }
+        Ok(Self { recording })
+    }
+}
+
+impl InputSource for ReplaySource {
+    fn start(self, sink: InputSink) -> (Vec<InputDevice>, Vec<thread::JoinHandle<()>>) {
+        let devices = self
+            .recording
+            .devices
+            .iter()
+            .map(|d| InputDevice::new(d.name.clone(), d.key.clone(), d.calibration))
+            .collect();
+        let recording = self.recording;
+        let handle = thread::spawn(move || {
+            let started = Instant::now();
+            recording.decode(|t, mut touch| {
+                if let Some(wait) = Duration::from_secs_f64(t.max(0.0)).checked_sub(started.elapsed()) {
+                    thread::sleep(wait);
+                }
+                // the recorded times are long past, latency is measured from the replay
+                touch.timestamp = Some(SystemTime::now());
+                sink.send_touch(touch)
+            });
+            log::info!("[Recording] Replay finished");
+        });
+        (devices, vec![handle])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{AppOptions, PaintingApp};
+    use eframe::egui;
+
+    const RECORDINGS: &str = "tests/recordings";
+    const DRAG: &str = "tests/recordings/synthetic-drag.jsonl";
+
+    fn recordings() -> Vec<String> {
+        let mut paths: Vec<String> = std::fs::read_dir(RECORDINGS)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    // runs frames the way eframe does until the replay has played out and its last touches are drawn
+    fn replay(path: &str) -> PaintingApp {
+        let ctx = egui::Context::default();
+        let mut app = PaintingApp::new(&ctx, AppOptions { replay_input: Some(path.to_string()), ..AppOptions::headless() });
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            let finished = app.input_handler.as_ref().is_none_or(|handler| handler.sources_finished());
+            let mut input = egui::RawInput {
+                screen_rect: Some(egui::Rect::from_min_size(Pos2::ZERO, egui::vec2(1280.0, 960.0))),
+                ..Default::default()
+            };
+            app.route_touch_input(&ctx, &mut input);
+            let _ = ctx.run(input, |ctx| app.show(ctx));
+            if finished {
+                return app;
+            }
+            assert!(Instant::now() < deadline, "{} didn't finish replaying", path);
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn every_recording_replays() {
+        let paths = recordings();
+        assert!(paths.iter().any(|path| path == DRAG));
+        for path in paths {
+            let recording = Recording::load(&path).unwrap();
+            assert!(recording.decoded_touches() == recording.touches, "{} decodes differently now", path);
+            let app = replay(&path);
+            assert!(app.active_touches.is_empty(), "{} left a contact down", path);
+        }
+    }
+
+    #[test]
+    fn replayed_drag_draws_one_stroke() {
+        let recording = Recording::load(DRAG).unwrap();
+        assert_eq!(recording.touches.first().map(|t| t.state), Some(TouchState::Began));
+        assert_eq!(recording.touches.last().map(|t| t.state), Some(TouchState::Ended));
+
+        let app = replay(DRAG);
+        assert_eq!(app.frames[0].len(), 1);
+        assert!(app.frames[0][0].points.len() >= 2);
+        assert!(app.frames[1..].iter().all(|frame| frame.is_empty()));
+    }
+}
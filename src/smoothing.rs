@@ -0,0 +1,281 @@
+use eframe::egui::{Pos2, Rect};
+use crate::models::Stroke;
+use crate::utils::{canvas_to_screen, distance_to_line_segment, screen_to_canvas};
+use std::collections::VecDeque;
+
+// fitted curves get a point about every this many screen pixels
+const CURVE_STEP: f32 = 3.0;
+const MAX_CURVE_STEPS: usize = 16;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum StabilizerMode {
+    Off,
+    // each point is the mean of the last few reports
+    MovingAverage,
+    // the brush trails the finger on a string and only moves once it's pulled taut
+    LazyBrush,
+}
+
+impl StabilizerMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            StabilizerMode::Off => "Off",
+            StabilizerMode::MovingAverage => "Moving average",
+            StabilizerMode::LazyBrush => "Lazy brush",
+        }
+    }
+}
+
+pub struct SmoothingSettings {
+    pub stabilizer: StabilizerMode,
+    // reports averaged by the moving average
+    pub window: usize,
+    // string length of the lazy brush, in screen pixels
+    pub lazy_radius: f32,
+    // replace the corners between points with a curve once the stroke ends
+    pub fit_curves: bool,
+    // points closer than this (screen pixels) to the simplified line are dropped, 0 keeps them all
+    pub simplify_tolerance: f32,
+}
+
+impl Default for SmoothingSettings {
+    fn default() -> Self {
+        Self {
+            stabilizer: StabilizerMode::MovingAverage,
+            window: 4,
+            lazy_radius: 8.0,
+            fit_curves: true,
+            simplify_tolerance: 0.5,
+        }
+    }
+}
+
+/// Per-contact state of the live stabilizer, positions in screen space.
+pub struct Stabilizer {
+    recent: VecDeque<Pos2>,
+    brush: Pos2,
+}
+
+impl Stabilizer {
+    pub fn new(pos: Pos2) -> Self {
+        Self { recent: VecDeque::from([pos]), brush: pos }
+    }
+
+    /// Where the brush goes for this report, None while the lazy brush hasn't moved.
+    pub fn push(&mut self, pos: Pos2, settings: &SmoothingSettings) -> Option<Pos2> {
+        match settings.stabilizer {
+            StabilizerMode::Off => Some(pos),
+            StabilizerMode::MovingAverage => {
+                self.recent.push_back(pos);
+                while self.recent.len() > settings.window.max(1) {
+                    self.recent.pop_front();
+                }
+                let sum = self.recent.iter().fold(Pos2::ZERO, |sum, p| sum + p.to_vec2());
+                Some(sum / self.recent.len() as f32)
+            }
+            StabilizerMode::LazyBrush => {
+                let distance = self.brush.distance(pos);
+                if distance <= settings.lazy_radius {
+                    return None;
+                }
+                self.brush = pos + (self.brush - pos) * (settings.lazy_radius / distance);
+                Some(self.brush)
+            }
+        }
+    }
+}
+
+/// Curve fitting and point reduction for a finished stroke. Works in screen space so the
+/// tolerances are in pixels, widths are carried along with their points.
+pub fn finish_stroke(stroke: &mut Stroke, settings: &SmoothingSettings, canvas_rect: Rect) {
+    if stroke.points.len() < 3 {
+        return;
+    }
+    let tapered = stroke.is_tapered();
+    let to_screen = canvas_to_screen(canvas_rect);
+    let canvas_width = canvas_rect.width();
+    let mut samples: Vec<(Pos2, f32)> = stroke
+        .points
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (to_screen * *p, stroke.width_at(i) * canvas_width))
+        .collect();
+    samples.dedup_by(|b, a| a.0 == b.0);
+    let original = stroke.points.len();
+
+    if settings.fit_curves {
+        samples = catmull_rom_spline(&samples);
+    }
+    if settings.simplify_tolerance > 0.0 {
+        samples = simplify(&samples, settings.simplify_tolerance);
+    }
+
+    let to_canvas = screen_to_canvas(canvas_rect);
+    stroke.points = samples.iter().map(|(p, _)| to_canvas * *p).collect();
+    stroke.widths = if tapered { samples.iter().map(|(_, w)| w / canvas_width).collect() } else { Vec::new() };
+    log::debug!("[Smoothing] Stroke finished with {} points, {} before", stroke.points.len(), original);
+}
+
+// centripetal Catmull-Rom through every sample, it doesn't overshoot or loop on unevenly spaced reports
+fn catmull_rom_spline(samples: &[(Pos2, f32)]) -> Vec<(Pos2, f32)> {
+    if samples.len() < 3 {
+        return samples.to_vec();
+    }
+    let count = samples.len();
+    // mirrored phantom points at both ends so the curve starts and ends on the stroke
+    let point = |i: isize| -> Pos2 {
+        if i < 0 {
+            samples[0].0 + (samples[0].0 - samples[1].0)
+        } else if i as usize >= count {
+            samples[count - 1].0 + (samples[count - 1].0 - samples[count - 2].0)
+        } else {
+            samples[i as usize].0
+        }
+    };
+
+    let mut out = Vec::with_capacity(count * 2);
+    for i in 0..count - 1 {
+        let (p1, w1) = samples[i];
+        let (p2, w2) = samples[i + 1];
+        let p0 = point(i as isize - 1);
+        let p3 = point(i as isize + 2);
+        let steps = ((p1.distance(p2) / CURVE_STEP).ceil() as usize).clamp(1, MAX_CURVE_STEPS);
+        for step in 0..steps {
+            let t = step as f32 / steps as f32;
+            out.push((centripetal_point(p0, p1, p2, p3, t), w1 + (w2 - w1) * t));
+        }
+    }
+    out.push(samples[count - 1]);
+    out
+}
+
+// point at `t` (0-1) between p1 and p2, Barry and Goldman's pyramid with sqrt-distance knots
+fn centripetal_point(p0: Pos2, p1: Pos2, p2: Pos2, p3: Pos2, t: f32) -> Pos2 {
+    let knot = |a: Pos2, b: Pos2| a.distance(b).sqrt().max(1e-4);
+    let t0 = 0.0;
+    let t1 = t0 + knot(p0, p1);
+    let t2 = t1 + knot(p1, p2);
+    let t3 = t2 + knot(p2, p3);
+    let t = t1 + (t2 - t1) * t;
+
+    let lerp = |a: Pos2, b: Pos2, ta: f32, tb: f32| a + (b - a) * ((t - ta) / (tb - ta));
+    let a1 = lerp(p0, p1, t0, t1);
+    let a2 = lerp(p1, p2, t1, t2);
+    let a3 = lerp(p2, p3, t2, t3);
+    let b1 = lerp(a1, a2, t0, t2);
+    let b2 = lerp(a2, a3, t1, t3);
+    lerp(b1, b2, t1, t2)
+}
+
+// Ramer-Douglas-Peucker, iterative so long strokes can't blow the stack
+fn simplify(samples: &[(Pos2, f32)], tolerance: f32) -> Vec<(Pos2, f32)> {
+    if samples.len() < 3 {
+        return samples.to_vec();
+    }
+    let mut keep = vec![false; samples.len()];
+    keep[0] = true;
+    keep[samples.len() - 1] = true;
+
+    let mut ranges = vec![(0, samples.len() - 1)];
+    while let Some((first, last)) = ranges.pop() {
+        let ((start, start_width), (end, end_width)) = (samples[first], samples[last]);
+        // a point that only changes the width still counts, half of it shows on each side
+        let error = |i: usize| {
+            let t = (i - first) as f32 / (last - first) as f32;
+            let width_error = (samples[i].1 - (start_width + (end_width - start_width) * t)).abs() / 2.0;
+            distance_to_line_segment(samples[i].0, start, end).max(width_error)
+        };
+        let farthest = (first + 1..last).map(|i| (i, error(i))).max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((index, distance)) = farthest {
+            if distance > tolerance {
+                keep[index] = true;
+                ranges.push((first, index));
+                ranges.push((index, last));
+            }
+        }
+    }
+
+    samples.iter().zip(keep).filter(|(_, keep)| *keep).map(|(sample, _)| *sample).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples(points: &[(f32, f32)]) -> Vec<(Pos2, f32)> {
+        points.iter().map(|&(x, y)| (Pos2::new(x, y), 2.0)).collect()
+    }
+
+    fn close(a: Pos2, b: Pos2) -> bool {
+        a.distance(b) < 1e-3
+    }
+
+    #[test]
+    fn simplify_drops_points_along_a_line_and_keeps_corners() {
+        // a wobbly horizontal line, then a right angle down
+        let mut points: Vec<(f32, f32)> = (0..50).map(|i| (i as f32 * 2.0, if i % 2 == 0 { 0.1 } else { -0.1 })).collect();
+        points.extend((1..=25).map(|i| (98.0, i as f32 * 2.0)));
+        let line = samples(&points);
+
+        let simplified = simplify(&line, 0.5);
+        assert!(simplified.len() < line.len());
+        assert_eq!(simplified.len(), 3);
+        assert!(close(simplified[1].0, Pos2::new(98.0, -0.1)));
+        assert!(close(simplified[0].0, line[0].0));
+        assert!(close(simplified[2].0, line[line.len() - 1].0));
+    }
+
+    #[test]
+    fn simplify_keeps_width_changes() {
+        let mut line = samples(&[(0.0, 0.0), (10.0, 0.0), (20.0, 0.0)]);
+        line[1].1 = 6.0;
+        assert_eq!(simplify(&line, 0.5).len(), 3);
+    }
+
+    #[test]
+    fn catmull_rom_passes_through_its_control_points() {
+        let controls = samples(&[(0.0, 0.0), (30.0, 10.0), (45.0, 50.0), (10.0, 60.0), (12.0, 61.0)]);
+        let curve = catmull_rom_spline(&controls);
+        assert!(curve.len() > controls.len());
+        for (point, width) in &controls {
+            assert!(curve.iter().any(|(p, w)| close(*p, *point) && w == width));
+        }
+        assert!(close(curve[0].0, controls[0].0));
+        assert!(close(curve[curve.len() - 1].0, controls[controls.len() - 1].0));
+    }
+
+    #[test]
+    fn finished_strokes_keep_their_endpoints() {
+        let canvas_rect = Rect::from_min_size(Pos2::new(50.0, 20.0), eframe::egui::vec2(400.0, 300.0));
+        let points: Vec<Pos2> = (0..40).map(|i| Pos2::new(0.1 + i as f32 * 0.02, 0.5 + (i as f32 * 0.3).sin() * 0.1)).collect();
+        let mut stroke = Stroke { points: points.clone(), ..Default::default() };
+
+        finish_stroke(&mut stroke, &SmoothingSettings { simplify_tolerance: 2.0, ..Default::default() }, canvas_rect);
+        assert!(stroke.points.len() < points.len());
+        assert!(close(stroke.points[0], points[0]));
+        assert!(close(stroke.points[stroke.points.len() - 1], points[points.len() - 1]));
+    }
+
+    #[test]
+    fn moving_average_follows_the_last_reports() {
+        let settings = SmoothingSettings { stabilizer: StabilizerMode::MovingAverage, window: 3, ..Default::default() };
+        let mut stabilizer = Stabilizer::new(Pos2::new(0.0, 0.0));
+        assert_eq!(stabilizer.push(Pos2::new(3.0, 0.0), &settings), Some(Pos2::new(1.5, 0.0)));
+        assert_eq!(stabilizer.push(Pos2::new(6.0, 3.0), &settings), Some(Pos2::new(3.0, 1.0)));
+        // the first report has left the window
+        assert_eq!(stabilizer.push(Pos2::new(9.0, 0.0), &settings), Some(Pos2::new(6.0, 1.0)));
+    }
+
+    #[test]
+    fn lazy_brush_trails_the_finger_by_its_radius() {
+        let settings = SmoothingSettings { stabilizer: StabilizerMode::LazyBrush, lazy_radius: 8.0, ..Default::default() };
+        let mut stabilizer = Stabilizer::new(Pos2::new(0.0, 0.0));
+        // slack in the string, the brush stays put
+        assert_eq!(stabilizer.push(Pos2::new(5.0, 0.0), &settings), None);
+        assert_eq!(stabilizer.push(Pos2::new(0.0, 8.0), &settings), None);
+        let brush = stabilizer.push(Pos2::new(20.0, 0.0), &settings).unwrap();
+        assert!(close(brush, Pos2::new(12.0, 0.0)));
+        let brush = stabilizer.push(Pos2::new(20.0, 30.0), &settings).unwrap();
+        assert!((brush.distance(Pos2::new(20.0, 30.0)) - 8.0).abs() < 1e-3);
+    }
+}
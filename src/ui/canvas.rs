@@ -1,36 +1,25 @@
 use eframe::egui::{self, Color32, Pos2, Sense, Stroke as EguiStroke};
 use crate::app::{PaintingApp, ToolMode};
-use crate::models::{Stroke as DrawingStroke, StrokeType};
-use crate::input::TouchState;
+use crate::models::{Stroke as DrawingStroke, StrokeType, in_paint_order};
+use crate::input::{ContactTool, TouchInput, TouchState};
+use crate::utils::{canvas_to_screen, screen_to_canvas};
+use crate::pointer::PointerEvent;
+use crate::smoothing::{finish_stroke, Stabilizer};
+use crate::history::EditCommand;
+use crate::palm::REJECTED_OVERLAY_SECONDS;
 
 pub fn draw_canvas(app: &mut PaintingApp, ui: &mut egui::Ui) {
     let panel_rect = ui.available_rect_before_wrap();
     let calculated_canvas_rect = app.calculate_aspect_ratio_rect(panel_rect);
 
-    if app.original_canvas_rect.is_none() {
-        app.original_canvas_rect = Some(calculated_canvas_rect);
-        app.canvas_rect = Some(calculated_canvas_rect);
-        log::info!("Canvas Initialized: original_rect and canvas_rect set to {:?}", calculated_canvas_rect);
-    } else {
-        let last_original_rect = app.original_canvas_rect.unwrap();
-        if last_original_rect != calculated_canvas_rect {
-            log::info!(
-                "Canvas area changed. Old basis: {:?}, New target drawing area: {:?}",
-                last_original_rect,
-                calculated_canvas_rect
-            );
-
-            app.recalculate_strokes_relative_to(last_original_rect, calculated_canvas_rect);
-            app.original_canvas_rect = Some(calculated_canvas_rect);
-            log::info!("Strokes recalculated. New basis: {:?}", app.original_canvas_rect);
-        }
+    if app.canvas_rect != Some(calculated_canvas_rect) {
+        log::info!("Canvas area changed: {:?} -> {:?}", app.canvas_rect, calculated_canvas_rect);
         app.canvas_rect = Some(calculated_canvas_rect);
     }
-    
-    let current_draw_canvas_rect = app.canvas_rect.unwrap_or_else(|| {
-        log::error!("[Canvas] app.canvas_rect is None before drawing. Defaulting to panel_rect.");
-        panel_rect
-    });
+    let current_draw_canvas_rect = calculated_canvas_rect;
+    let to_canvas = screen_to_canvas(current_draw_canvas_rect);
+
+    app.handle_view_wheel(ui.ctx(), current_draw_canvas_rect);
 
     let (_response, painter) = ui.allocate_painter(panel_rect.size(), Sense::click_and_drag());
 
@@ -38,106 +27,164 @@ pub fn draw_canvas(app: &mut PaintingApp, ui: &mut egui::Ui) {
     painter.rect_filled(current_draw_canvas_rect, 0.0, Color32::WHITE);
     painter.rect_stroke(current_draw_canvas_rect, 0.0, EguiStroke::new(1.0, Color32::BLACK));
 
+    // strokes are drawn through the zoom and pan, everything else works on the unzoomed canvas
+    let view_canvas_rect = app.view_canvas_rect(current_draw_canvas_rect);
+    let canvas_painter = painter.with_clip_rect(current_draw_canvas_rect);
+
     if app.show_onion_skin && !app.playing_animation {
-        app.draw_onion_skins(&painter);
+        app.draw_onion_skins(&canvas_painter, view_canvas_rect);
     }
 
-    for stroke in &app.frames[app.current_frame] {
-        app.draw_stroke(&painter, stroke);
+    for stroke in in_paint_order(&app.frames[app.current_frame]) {
+        app.draw_stroke(&canvas_painter, stroke, view_canvas_rect);
     }
 
+    // contacts during playback are dropped rather than replayed once it stops
+    let events = app.collect_pointer_events(ui.ctx(), current_draw_canvas_rect);
+    let mut gestures = Vec::new();
+    let events = app.gestures.process(events, current_draw_canvas_rect, ui.ctx().input(|i| i.time), &mut gestures);
+    for gesture in &gestures {
+        app.apply_gesture(gesture, current_draw_canvas_rect);
+    }
+    let from_view = app.view.inverse();
+
     if !app.playing_animation {
-        if let Some(input_handler) = &app.input_handler {
-            let events = input_handler.get_events();
-
-            for event in events {
-                let original_norm_pos = event.pos;
-
-                let screen_rect = ui.ctx().screen_rect();
-                let mut final_norm_pos = original_norm_pos;
-
-                let is_portrait = screen_rect.height() > screen_rect.width();
-                if is_portrait {
-                    if app.invert_input {
-                        final_norm_pos.x = 1.0 - original_norm_pos.y;
-                        final_norm_pos.y = original_norm_pos.x;
-                        log::trace!("[Canvas] Portrait input (inverted): T({:.2},{:.2}) -> S({:.2},{:.2})",
-                                    original_norm_pos.x, original_norm_pos.y, final_norm_pos.x, final_norm_pos.y);
-                    } else {
-                        final_norm_pos.x = original_norm_pos.y;
-                        final_norm_pos.y = 1.0 - original_norm_pos.x;
-                        log::trace!("[Canvas] Portrait input (default): T({:.2},{:.2}) -> S({:.2},{:.2})",
-                                    original_norm_pos.x, original_norm_pos.y, final_norm_pos.x, final_norm_pos.y);
-                    }
-                } else {
-                    log::trace!("[Canvas] Landscape input: T({:.2},{:.2}) -> S({:.2},{:.2})",
-                                original_norm_pos.x, original_norm_pos.y, final_norm_pos.x, final_norm_pos.y);
+        for event in events {
+            match event.state {
+                TouchState::Hover => {
+                    app.hover_cursor = Some((event.pos, event.tool));
+                    continue;
+                }
+                TouchState::HoverEnded => {
+                    app.hover_cursor = None;
+                    continue;
                 }
+                TouchState::Began if event.tool != ContactTool::Finger => app.hover_cursor = None,
+                _ => {}
+            }
+            match event.state {
+                TouchState::Ended => app.history.finish_contact(event.id),
+                TouchState::Cancelled => app.discard_contact_edits(event.id),
+                _ => {}
+            }
 
-                let pos_on_screen = Pos2::new(
-                    screen_rect.min.x + final_norm_pos.x * screen_rect.width(),
-                    screen_rect.min.y + final_norm_pos.y * screen_rect.height(),
-                );
-
-                if app.tool_mode == ToolMode::Brush {
-                    match event.state {
-                        TouchState::Began => {
-                            if current_draw_canvas_rect.contains(pos_on_screen) {
-                                let stroke = DrawingStroke {
-                                    points: vec![pos_on_screen],
-                                    color: app.brush_color,
-                                    size: app.brush_size,
-                                    stroke_type: StrokeType::Draw,
-                                };
-                                app.active_touches.insert(event.id, stroke);
-                            }
-                        }
-                        TouchState::Moved => {
-                            if let Some(stroke) = app.active_touches.get_mut(&event.id) {
-                                if current_draw_canvas_rect.contains(pos_on_screen) {
-                                    stroke.points.push(pos_on_screen);
-                                }
-                            }
+            let on_canvas = current_draw_canvas_rect.contains(event.pos);
+            let pos_on_screen = from_view * event.pos;
+
+            // the pen's rubber end erases for that contact only, whatever tool is selected
+            let tool = if event.tool == ContactTool::Eraser { ToolMode::Eraser } else { app.tool_mode };
+
+            if tool == ToolMode::Brush {
+                match event.state {
+                    TouchState::Began => {
+                        if on_canvas {
+                            let stroke = DrawingStroke {
+                                points: vec![to_canvas * pos_on_screen],
+                                color: app.brush_color,
+                                size: app.brush_size / current_draw_canvas_rect.width(),
+                                stroke_type: StrokeType::Draw,
+                                widths: vec![contact_width(app.brush_size, &event, None) / current_draw_canvas_rect.width()],
+                            };
+                            app.active_touches.insert(event.id, stroke);
+                            app.stabilizers.insert(event.id, Stabilizer::new(pos_on_screen));
                         }
-                        TouchState::Ended => {
-                            if let Some(mut stroke_to_finalize) = app.active_touches.remove(&event.id) {
-                                if current_draw_canvas_rect.contains(pos_on_screen) {
-                                    if stroke_to_finalize.points.last() != Some(&pos_on_screen) {
-                                        stroke_to_finalize.points.push(pos_on_screen);
-                                    }
-                                }
-                                if !stroke_to_finalize.points.is_empty() {
-                                    app.draw_stroke(&painter, &stroke_to_finalize); // FLICKER FIX
-                                    app.save_state_for_undo(); 
-                                    app.frames[app.current_frame].push(stroke_to_finalize);
-                                }
+                    }
+                    TouchState::Moved => {
+                        if let Some(stroke) = app.active_touches.get_mut(&event.id) {
+                            let stabilized = match app.stabilizers.get_mut(&event.id) {
+                                Some(stabilizer) => stabilizer.push(pos_on_screen, &app.smoothing),
+                                None => Some(pos_on_screen),
+                            };
+                            if let Some(pos) = stabilized.filter(|p| current_draw_canvas_rect.contains(app.view * *p)) {
+                                let previous = last_point_on_screen(stroke, current_draw_canvas_rect);
+                                let width = contact_width(app.brush_size, &event, previous.map(|p| (p, pos)));
+                                stroke.widths.push(width / current_draw_canvas_rect.width());
+                                stroke.points.push(to_canvas * pos);
                             }
                         }
                     }
-                }
-                else if app.tool_mode == ToolMode::Eraser {
-                    if current_draw_canvas_rect.contains(pos_on_screen) {
-                        log::trace!("[Canvas] Eraser event (id={}, state={:?}) pos_on_screen=({:.2},{:.2}) inside canvas.", event.id, event.state, pos_on_screen.x, pos_on_screen.y);
-                        match event.state {
-                            TouchState::Moved | TouchState::Began => {
-                                let eraser_size = app.brush_size * 2.0;
-                                log::debug!("[Canvas] Eraser Active: pos_on_screen=({:.2},{:.2}), size={}", pos_on_screen.x, pos_on_screen.y, eraser_size);
-                                app.erase_strokes_at_position(pos_on_screen, eraser_size);
+                    TouchState::Ended => {
+                        app.stabilizers.remove(&event.id);
+                        // the lift position is added as is, so a trailing stabilizer catches up with the finger
+                        if let Some(mut stroke_to_finalize) = app.active_touches.remove(&event.id) {
+                            let pos_on_canvas = to_canvas * pos_on_screen;
+                            if on_canvas
+                                && stroke_to_finalize.points.last() != Some(&pos_on_canvas)
+                            {
+                                let previous = last_point_on_screen(&stroke_to_finalize, current_draw_canvas_rect);
+                                let width = contact_width(app.brush_size, &event, previous.map(|p| (p, pos_on_screen)));
+                                stroke_to_finalize.widths.push(width / current_draw_canvas_rect.width());
+                                stroke_to_finalize.points.push(pos_on_canvas);
                             }
-                            TouchState::Ended => {
-                                log::debug!("[Canvas] Eraser Ended event for id={}", event.id);
+                            if !stroke_to_finalize.points.is_empty() {
+                                finish_stroke(&mut stroke_to_finalize, &app.smoothing, current_draw_canvas_rect);
+                                app.draw_stroke(&canvas_painter, &stroke_to_finalize, view_canvas_rect); // FLICKER FIX
+                                app.execute(EditCommand::AddStroke { frame: app.current_frame, stroke: stroke_to_finalize });
                             }
                         }
-                        let radius = app.brush_size * 1.0;
-                        painter.circle_stroke(pos_on_screen, radius, EguiStroke::new(1.0, Color32::from_rgba_premultiplied(255,0,0,100)));
                     }
+                    TouchState::Cancelled => {
+                        app.stabilizers.remove(&event.id);
+                        if app.active_touches.remove(&event.id).is_some() {
+                            log::debug!("[Canvas] Discarded stroke of cancelled contact {:?}", event.id);
+                        }
+                    }
+                    TouchState::Hover | TouchState::HoverEnded => {}
                 }
             }
-        } else { log::trace!("[Canvas] Input handler not available."); }
+            else if tool == ToolMode::Eraser && on_canvas {
+                log::trace!("[Canvas] Eraser event (id={:?}, state={:?}) pos_on_screen=({:.2},{:.2}) inside canvas.", event.id, event.state, pos_on_screen.x, pos_on_screen.y);
+                match event.state {
+                    TouchState::Moved | TouchState::Began => {
+                        let eraser_size = app.brush_size * 2.0;
+                        log::debug!("[Canvas] Eraser Active: pos_on_screen=({:.2},{:.2}), size={}", pos_on_screen.x, pos_on_screen.y, eraser_size);
+                        app.erase_strokes_at_position(pos_on_screen, eraser_size, event.id);
+                    }
+                    TouchState::Ended | TouchState::Cancelled => {
+                        log::debug!("[Canvas] Eraser {:?} event for id={:?}", event.state, event.id);
+                    }
+                    TouchState::Hover | TouchState::HoverEnded => {}
+                }
+                let radius = app.brush_size * 1.0;
+                painter.circle_stroke(event.pos, radius * app.view.scaling, EguiStroke::new(1.0, Color32::from_rgba_premultiplied(255,0,0,100)));
+            }
+            // fill on release, so a contact the palm filter cancels never floods the frame
+            else if tool == ToolMode::Fill
+                && event.state == TouchState::Ended
+                && on_canvas
+            {
+                log::debug!("[Canvas] Fill requested at pos_on_screen=({:.2},{:.2})", pos_on_screen.x, pos_on_screen.y);
+                app.fill_at_position(pos_on_screen);
+            }
+        }
+
+        for stroke in app.active_touches.values() {
+            app.draw_stroke(&canvas_painter, stroke, view_canvas_rect);
+        }
 
-        for (_id, stroke) in &app.active_touches {
-            if app.tool_mode == ToolMode::Brush {
-                app.draw_stroke(&painter, stroke);
+        if let Some((pos, tool)) = app.hover_cursor {
+            if current_draw_canvas_rect.contains(pos) {
+                let (radius, color) = if tool == ContactTool::Eraser || app.tool_mode == ToolMode::Eraser {
+                    (app.brush_size, Color32::from_rgba_premultiplied(255, 0, 0, 100))
+                } else {
+                    (app.brush_size / 2.0, app.brush_color)
+                };
+                painter.circle_stroke(pos, (radius * app.view.scaling).max(1.0), EguiStroke::new(1.0, color));
+            }
+        }
+
+        if app.palm_filter.settings.show_overlay {
+            let screen_rect = ui.ctx().screen_rect();
+            let now = ui.ctx().input(|i| i.time);
+            for rejected in &app.palm_filter.rejected {
+                let fade = 1.0 - ((now - rejected.at) / REJECTED_OVERLAY_SECONDS).clamp(0.0, 1.0) as f32;
+                let color = Color32::RED.gamma_multiply(fade);
+                let pos = touch_to_screen(screen_rect, &rejected.touch);
+                painter.circle_stroke(pos, 24.0, EguiStroke::new(2.0, color));
+                painter.text(pos + egui::vec2(0.0, 30.0), egui::Align2::CENTER_TOP, rejected.reason.label(), egui::FontId::proportional(14.0), color);
+            }
+            if !app.palm_filter.rejected.is_empty() {
+                ui.ctx().request_repaint();
             }
         }
     }
@@ -153,4 +200,45 @@ pub fn draw_canvas(app: &mut PaintingApp, ui: &mut egui::Ui) {
             Color32::BLACK,
         );
     }
-}
\ No newline at end of file
+}
+
+// pressure scales the brush between these factors, so a medium press draws at about the picked size
+const PRESSURE_SCALE_RANGE: (f32, f32) = (0.3, 1.7);
+// without pressure or contact size, fast strokes thin out towards this factor
+const VELOCITY_MIN_SCALE: f32 = 0.4;
+// on-screen distance between two reports at which the velocity fallback is at its thinnest
+const VELOCITY_THIN_DISTANCE: f32 = 40.0;
+// how much of the new target width each point takes, the rest comes from the previous point
+const WIDTH_SMOOTHING: f32 = 0.35;
+
+// the last point and its width, both in screen pixels
+fn last_point_on_screen(stroke: &DrawingStroke, canvas_rect: egui::Rect) -> Option<(Pos2, f32)> {
+    let to_screen = canvas_to_screen(canvas_rect);
+    let point = *stroke.points.last()?;
+    let width = stroke.widths.last().copied().unwrap_or(stroke.size);
+    Some((to_screen * point, width * canvas_rect.width()))
+}
+
+// `segment` is the previous point with its width and the new point, both on screen
+fn contact_width(brush_size: f32, event: &PointerEvent, segment: Option<((Pos2, f32), Pos2)>) -> f32 {
+    let (min_scale, max_scale) = PRESSURE_SCALE_RANGE;
+    let target = match event.pressure.or(event.contact_size) {
+        Some(amount) => brush_size * (min_scale + (max_scale - min_scale) * amount),
+        None => {
+            let distance = segment.map_or(0.0, |((previous, _), pos)| previous.distance(pos));
+            let t = (distance / VELOCITY_THIN_DISTANCE).min(1.0);
+            brush_size * (1.0 + (VELOCITY_MIN_SCALE - 1.0) * t)
+        }
+    };
+
+    match segment {
+        Some(((_, previous_width), _)) => previous_width + (target - previous_width) * WIDTH_SMOOTHING,
+        None => target,
+    }
+}
+
+// InputHandler maps touches onto the window (0-1), touches that didn't pass through it are taken as they are
+pub fn touch_to_screen(screen_rect: egui::Rect, event: &TouchInput) -> Pos2 {
+    let norm_pos = event.screen_pos.unwrap_or(event.pos);
+    screen_rect.min + norm_pos.to_vec2() * screen_rect.size()
+}
@@ -1,5 +1,6 @@
 pub mod panels;
 pub mod canvas;
+mod touch_pointer;
 
 pub use panels::*;
 pub use canvas::*;
\ No newline at end of file
@@ -1,5 +1,9 @@
-use eframe::egui::{self, Color32, FontFamily, FontId, RichText, Vec2};
-use crate::app::{PaintingApp, ToolMode};
+use eframe::egui::{self, Color32, FontFamily, FontId, Pos2, RichText, Vec2};
+use crate::app::{FrameDrag, FrameDragPhase, PaintingApp, ToolMode, MAX_FRAMES};
+use crate::input::TouchState;
+use crate::pointer::PointerId;
+use crate::export::{ExportTarget, EXPORT_SIZE_RANGE};
+use crate::smoothing::StabilizerMode;
 
 pub fn draw_left_panel(app: &mut PaintingApp, ctx: &egui::Context, panel_ui: &mut egui::Ui) {
     let mut style = (*ctx.style()).clone();
@@ -51,6 +55,91 @@ pub fn draw_left_panel(app: &mut PaintingApp, ctx: &egui::Context, panel_ui: &mu
                 "Note: Could not determine local IP."
             }
         );
+        panel_ui.add_space(15.0);
+
+        panel_ui.heading("Touch Calibration");
+        let mut cleared = None;
+        if let Some(handler) = &app.input_handler {
+            panel_ui.label(format!("Orientation without calibration: {}", handler.touch_transform().label()));
+            for (index, device) in handler.devices().iter().enumerate() {
+                panel_ui.horizontal(|ui| {
+                    let calibrated = app.calibrations.devices.contains_key(&device.key);
+                    ui.label(format!(
+                        "{}: {}{}",
+                        device.name,
+                        if calibrated { "calibrated" } else { "default mapping" },
+                        if handler.is_grabbed(index) { ", grabbed" } else { "" }
+                    ));
+                    if calibrated && ui.small_button("Reset").clicked() {
+                        cleared = Some(index);
+                    }
+                });
+            }
+        }
+        if let Some(index) = cleared {
+            app.clear_calibration(index, ctx);
+        }
+        if panel_ui.add_enabled(app.input_handler.is_some(), egui::Button::new("Calibrate Touch")).clicked() {
+            app.start_calibration();
+        }
+        panel_ui.add_space(15.0);
+
+        panel_ui.heading("Smoothing");
+        let smoothing = &mut app.smoothing;
+        panel_ui.horizontal(|ui| {
+            ui.label("Stabilizer:");
+            egui::ComboBox::from_id_salt("stabilizer")
+                .selected_text(smoothing.stabilizer.label())
+                .show_ui(ui, |ui| {
+                    for mode in [StabilizerMode::Off, StabilizerMode::MovingAverage, StabilizerMode::LazyBrush] {
+                        ui.selectable_value(&mut smoothing.stabilizer, mode, mode.label());
+                    }
+                });
+        });
+        match smoothing.stabilizer {
+            StabilizerMode::MovingAverage => {
+                panel_ui.horizontal(|ui| {
+                    ui.label("Averaged reports:");
+                    ui.add(egui::Slider::new(&mut smoothing.window, 1..=16));
+                });
+            }
+            StabilizerMode::LazyBrush => {
+                panel_ui.horizontal(|ui| {
+                    ui.label("String length:");
+                    ui.add(egui::Slider::new(&mut smoothing.lazy_radius, 1.0..=40.0).suffix(" px"));
+                });
+            }
+            StabilizerMode::Off => {}
+        }
+        panel_ui.checkbox(&mut smoothing.fit_curves, "Round off corners when a stroke ends");
+        panel_ui.horizontal(|ui| {
+            ui.label("Simplify tolerance:");
+            ui.add(egui::Slider::new(&mut smoothing.simplify_tolerance, 0.0..=3.0).suffix(" px"));
+        });
+        panel_ui.add_space(15.0);
+
+        panel_ui.heading("Palm Rejection");
+        let palm = &mut app.palm_filter.settings;
+        panel_ui.checkbox(&mut palm.enabled, "Enabled");
+        panel_ui.add_enabled_ui(palm.enabled, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Max contact size:");
+                ui.add(egui::Slider::new(&mut palm.max_contact_size, 0.05..=1.0));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Min duration:");
+                let mut millis = palm.min_duration * 1000.0;
+                if ui.add(egui::DragValue::new(&mut millis).range(0.0..=500.0).suffix(" ms")).changed() {
+                    palm.min_duration = millis / 1000.0;
+                }
+            });
+            ui.checkbox(&mut palm.reject_near_pen, "Ignore touches while the pen is near");
+            ui.checkbox(&mut palm.show_overlay, "Show rejected contacts");
+        });
+        panel_ui.add_space(15.0);
+
+        panel_ui.heading("Diagnostics");
+        panel_ui.checkbox(&mut app.latency.show_overlay, "Show frame rate and touch latency");
 
     } else {
         let larger_font = FontId::new(18.0, FontFamily::Proportional);
@@ -99,26 +188,41 @@ pub fn draw_left_panel(app: &mut PaintingApp, ctx: &egui::Context, panel_ui: &mu
                                 RichText::new("Eraser").font(larger_font.clone()),
                             ));
 
+                            let fill_btn = ui.add(egui::SelectableLabel::new(
+                                matches!(app.tool_mode, ToolMode::Fill),
+                                RichText::new("Fill").font(larger_font.clone()),
+                            ));
+
                             if brush_btn.clicked() {
                                 app.tool_mode = ToolMode::Brush;
                             }
                             if eraser_btn.clicked() {
                                 app.tool_mode = ToolMode::Eraser;
                             }
+                            if fill_btn.clicked() {
+                                app.tool_mode = ToolMode::Fill;
+                            }
                         });
                         scroll_ui.add_space(1.0);
                         scroll_ui.separator();
                         scroll_ui.add_space(1.0);
 
                         scroll_ui.horizontal(|ui| {
-                            if ui.button("↩ Undo").clicked() {
+                            if ui.add_enabled(app.history.can_undo(), egui::Button::new("↩ Undo")).clicked() {
                                 app.undo();
                             }
-                            if ui.button("↪ Redo").clicked() {
+                            if ui.add_enabled(app.history.can_redo(), egui::Button::new("↪ Redo")).clicked() {
                                 app.redo();
                             }
                         });
 
+                        scroll_ui.horizontal(|ui| {
+                            let label = format!("🔍 Reset View ({:.0}%)", app.view.scaling * 100.0);
+                            if ui.add_enabled(app.is_view_zoomed(), egui::Button::new(label)).clicked() {
+                                app.reset_view();
+                            }
+                        });
+
                         scroll_ui.add_space(1.0);
                         scroll_ui.separator();
                         scroll_ui.add_space(1.0);
@@ -174,16 +278,11 @@ pub fn draw_left_panel(app: &mut PaintingApp, ctx: &egui::Context, panel_ui: &mu
                             .spacing([4.0, 4.0])
                             .show(scroll_ui, |ui| {
                                 if ui.button("Clear Frame").clicked() {
-                                    app.save_state_for_undo();
-                                    app.frames[app.current_frame].clear();
+                                    app.clear_current_frame();
                                 }
 
                                 if ui.button("Reset All Frames").clicked() {
-                                    app.save_state_for_undo();
-                                    app.current_frame = 0;
-                                    for frame in &mut app.frames {
-                                        frame.clear();
-                                    }
+                                    app.clear_all_frames();
                                 }
                                 ui.end_row();
 
@@ -201,6 +300,67 @@ pub fn draw_left_panel(app: &mut PaintingApp, ctx: &egui::Context, panel_ui: &mu
                         scroll_ui.separator();
                         scroll_ui.add_space(1.0);
 
+                        scroll_ui.heading("Project");
+                        scroll_ui.add_space(1.0);
+
+                        scroll_ui.horizontal(|ui| {
+                            ui.label("File:");
+                            ui.text_edit_singleline(&mut app.project_path);
+                        });
+
+                        scroll_ui.horizontal(|ui| {
+                            if ui.button("💾 Save Project").clicked() {
+                                let path = app.project_path.clone();
+                                match app.save_project(&path) {
+                                    Ok(()) => app.add_notification(format!("Project saved to {}", path), Color32::GREEN, 3.0, ctx),
+                                    Err(e) => app.add_notification(format!("Save failed: {}", e), Color32::RED, 5.0, ctx),
+                                }
+                            }
+                            if ui.button("📂 Load Project").clicked() {
+                                let path = app.project_path.clone();
+                                match app.load_project(&path) {
+                                    Ok(()) => app.add_notification(format!("Project loaded from {}", path), Color32::GREEN, 3.0, ctx),
+                                    Err(e) => app.add_notification(format!("Load failed: {}", e), Color32::RED, 5.0, ctx),
+                                }
+                            }
+                        });
+
+                        scroll_ui.add_space(1.0);
+                        scroll_ui.separator();
+                        scroll_ui.add_space(1.0);
+
+                        egui::ComboBox::from_label("Export To")
+                            .selected_text(app.export_target.label())
+                            .show_ui(scroll_ui, |ui| {
+                                for target in ExportTarget::ALL {
+                                    ui.selectable_value(&mut app.export_target, target, target.label());
+                                }
+                            });
+
+                        if app.export_target != ExportTarget::Server {
+                            scroll_ui.horizontal(|ui| {
+                                ui.label("Folder:");
+                                ui.text_edit_singleline(&mut app.export_dir);
+                            });
+                        }
+
+                        let mut fixed_export_size = app.export_size.is_some();
+                        if scroll_ui.checkbox(&mut fixed_export_size, "Fixed Export Size").changed() {
+                            app.export_size = if fixed_export_size {
+                                let canvas_size = app.canvas_rect.map(|r| r.size()).unwrap_or(Vec2::new(1080.0, 1440.0));
+                                Some([canvas_size.x as u32, canvas_size.y as u32])
+                            } else {
+                                None
+                            };
+                        }
+                        if let Some([width, height]) = &mut app.export_size {
+                            scroll_ui.horizontal(|ui| {
+                                ui.add(egui::DragValue::new(width).range(EXPORT_SIZE_RANGE).suffix(" px"));
+                                ui.label("×");
+                                ui.add(egui::DragValue::new(height).range(EXPORT_SIZE_RANGE).suffix(" px"));
+                            });
+                        }
+
                         let current_time = ctx.input(|i| i.time); 
                         let cooldown_remaining = app.export_cooldown - (current_time - app.last_export_time);
                         let in_cooldown = cooldown_remaining > 0.0;
@@ -224,7 +384,19 @@ pub fn draw_left_panel(app: &mut PaintingApp, ctx: &egui::Context, panel_ui: &mu
 }
 
 pub fn draw_frame_panel(app: &mut PaintingApp, ctx: &egui::Context) {
+    let now = ctx.input(|i| i.time);
+    update_frame_drag(app, ctx, now);
+
+    // with an evdev device the compositor also turns touches into pointer drags, so only
+    // the raw touch path may reorder frames
+    let pointer_drag_enabled = app.input_handler.is_none();
+    let dragging = app.frame_drag.as_ref().filter(|d| d.phase == FrameDragPhase::Dragging);
+    let drag_source = dragging.map(|d| d.from);
+    let drop_target = dragging.and_then(|d| app.frame_drop_index(d.pos));
+
     egui::TopBottomPanel::bottom("frame_panel").show(ctx, |ui| {
+        app.frame_thumb_rects.clear();
+
         ui.horizontal_wrapped(|ui| {
             for i in 0..app.frames.len() {
                 ui.vertical(|ui| {
@@ -233,18 +405,41 @@ pub fn draw_frame_panel(app: &mut PaintingApp, ctx: &egui::Context) {
                     let frame_size = 60.0;
                     let (rect, response) = ui.allocate_exact_size(
                         egui::vec2(frame_size, frame_size),
-                        egui::Sense::click(),
+                        if pointer_drag_enabled { egui::Sense::click_and_drag() } else { egui::Sense::click() },
                     );
+                    app.frame_thumb_rects.push(rect);
 
                     if response.clicked() {
                         app.current_frame = i;
                         app.playing_animation = false;
                     }
 
+                    if pointer_drag_enabled {
+                        if response.drag_started() && app.frame_drag.is_none() {
+                            if let Some(pos) = response.interact_pointer_pos() {
+                                let mut drag = FrameDrag::new(None, i, pos, now);
+                                drag.phase = FrameDragPhase::Dragging;
+                                app.frame_drag = Some(drag);
+                            }
+                        }
+                        if let Some(pos) = ctx.pointer_interact_pos() {
+                            if let Some(drag) = app.frame_drag.as_mut().filter(|d| d.touch_id.is_none()) {
+                                drag.pos = pos;
+                            }
+                        }
+                        if response.drag_stopped() {
+                            if let Some(drag) = app.frame_drag.take().filter(|d| d.touch_id.is_none()) {
+                                app.finish_frame_drag(drag);
+                            }
+                        }
+                    }
+
                     ui.painter().rect_filled(
                         rect,
                         0.0,
-                        if is_selected {
+                        if drag_source == Some(i) {
+                            Color32::YELLOW
+                        } else if is_selected {
                             Color32::RED
                         } else {
                             Color32::GRAY
@@ -257,9 +452,110 @@ pub fn draw_frame_panel(app: &mut PaintingApp, ctx: &egui::Context) {
                     let content_rect = app.calculate_thumbnail_rect(inner_rect.shrink(2.0));
                     app.draw_thumbnail_content(i, ui.painter(), content_rect);
 
+                    if drop_target == Some(i) && drag_source != Some(i) {
+                        let marker_x = if drag_source.is_some_and(|from| from < i) { rect.right() + 2.0 } else { rect.left() - 2.0 };
+                        ui.painter().line_segment(
+                            [egui::pos2(marker_x, rect.top()), egui::pos2(marker_x, rect.bottom())],
+                            egui::Stroke::new(3.0, Color32::YELLOW),
+                        );
+                    }
+
                     ui.label(format!("Frame {}", i + 1));
                 });
             }
         });
+
+        ui.horizontal(|ui| {
+            let can_add = app.frames.len() < MAX_FRAMES;
+            let can_remove = app.frames.len() > 1;
+            let current = app.current_frame;
+
+            if ui.add_enabled(can_add, egui::Button::new("➕ New Frame")).clicked() {
+                app.playing_animation = false;
+                app.insert_frame_after_current();
+            }
+            if ui.add_enabled(can_add, egui::Button::new("⧉ Duplicate")).clicked() {
+                app.playing_animation = false;
+                app.duplicate_current_frame();
+            }
+            if ui.add_enabled(can_remove, egui::Button::new("🗑 Delete")).clicked() {
+                app.playing_animation = false;
+                app.delete_current_frame();
+            }
+            if ui.add_enabled(current > 0, egui::Button::new("◀ Move Left")).clicked() {
+                app.playing_animation = false;
+                app.move_frame(current, current - 1);
+            }
+            if ui.add_enabled(current + 1 < app.frames.len(), egui::Button::new("Move Right ▶")).clicked() {
+                app.playing_animation = false;
+                app.move_frame(current, current + 1);
+            }
+        });
     });
-}
\ No newline at end of file
+}
+
+const LONG_PRESS_SECONDS: f64 = 0.4;
+const LONG_PRESS_SLOP: f32 = 15.0;
+
+// a touch that is still held turns into a drag even if the panel reports no movement
+fn update_frame_drag(app: &mut PaintingApp, ctx: &egui::Context, now: f64) {
+    let Some(drag) = &mut app.frame_drag else {
+        return;
+    };
+
+    if drag.phase == FrameDragPhase::Pressed && now - drag.started_at >= LONG_PRESS_SECONDS {
+        log::debug!("[FrameStrip] Long press on frame {}, dragging.", drag.from);
+        drag.phase = FrameDragPhase::Dragging;
+        app.playing_animation = false;
+    }
+
+    if drag.phase == FrameDragPhase::Dragging {
+        let (from, pos) = (drag.from, drag.pos);
+        let layer = egui::LayerId::new(egui::Order::Tooltip, egui::Id::new("frame_drag_ghost"));
+        let painter = ctx.layer_painter(layer);
+        let ghost_rect = egui::Rect::from_center_size(pos, egui::vec2(70.0, 70.0));
+        painter.rect_filled(ghost_rect, 4.0, Color32::from_rgba_unmultiplied(255, 255, 0, 200));
+        painter.rect_filled(ghost_rect.shrink(4.0), 0.0, Color32::WHITE);
+        let content_rect = app.calculate_thumbnail_rect(ghost_rect.shrink(6.0));
+        app.draw_thumbnail_content(from, &painter, content_rect);
+    }
+
+    ctx.request_repaint();
+}
+
+/// Feeds an evdev touch into the frame strip, returning true when the touch belongs to it
+/// and should not reach the drawing tools.
+pub fn handle_frame_strip_touch(app: &mut PaintingApp, id: PointerId, state: TouchState, pos: Pos2, now: f64) -> bool {
+    match state {
+        TouchState::Began => {
+            if app.frame_drag.is_some() {
+                return false;
+            }
+            let Some(index) = app.frame_thumb_rects.iter().position(|r| r.contains(pos)) else {
+                return false;
+            };
+            app.frame_drag = Some(FrameDrag::new(Some(id), index, pos, now));
+            true
+        }
+        TouchState::Moved => {
+            let Some(drag) = app.frame_drag.as_mut().filter(|d| d.touch_id == Some(id)) else {
+                return false;
+            };
+            drag.pos = pos;
+            if drag.phase == FrameDragPhase::Pressed && drag.start_pos.distance(pos) > LONG_PRESS_SLOP {
+                drag.phase = FrameDragPhase::Cancelled;
+            }
+            true
+        }
+        TouchState::Ended => {
+            let Some(mut drag) = app.frame_drag.take_if(|d| d.touch_id == Some(id)) else {
+                return false;
+            };
+            drag.pos = pos;
+            app.finish_frame_drag(drag);
+            true
+        }
+        TouchState::Cancelled => app.frame_drag.take_if(|d| d.touch_id == Some(id)).is_some(),
+        TouchState::Hover | TouchState::HoverEnded => false,
+    }
+}
@@ -0,0 +1,79 @@
+use eframe::egui::{self, Pos2};
+use crate::app::PaintingApp;
+use crate::input::{ContactTool, TouchInput, TouchState};
+use crate::pointer::PointerId;
+use crate::ui::touch_to_screen;
+
+impl PaintingApp {
+    /// Pulls this frame's touches off the input threads before egui sees the frame. Touches from
+    /// grabbed devices that land on the panels are turned into egui pointer events, the rest are
+    /// left in `pending_touches` for the canvas and the frame strip.
+    pub fn route_touch_input(&mut self, ctx: &egui::Context, raw_input: &mut egui::RawInput) {
+        let Some(handler) = &self.input_handler else {
+            return;
+        };
+        let events = handler.get_events();
+        let grabbed: Vec<bool> = (0..handler.devices().len()).map(|i| handler.is_grabbed(i)).collect();
+        let now = raw_input.time.unwrap_or_else(|| ctx.input(|i| i.time));
+        for timestamp in events.iter().filter_map(|e| e.timestamp) {
+            self.latency.touch(now, timestamp);
+        }
+        let screen_rect = raw_input.screen_rect.unwrap_or_else(|| ctx.screen_rect());
+
+        // calibration taps are quick and deliberate, the palm filter would cancel or drop them.
+        // They're handled here so nothing on the canvas, playback included, gets in the way
+        if self.calibration.is_some() {
+            for event in &events {
+                self.handle_calibration_touch(event, screen_rect, ctx);
+            }
+            return;
+        }
+
+        let events = self.palm_filter.filter(events, now);
+        for event in events {
+            if grabbed[event.device] {
+                let pos = touch_to_screen(screen_rect, &event);
+                if self.touch_to_pointer(&event, pos, raw_input) {
+                    continue;
+                }
+            }
+            self.pending_touches.push(event);
+        }
+    }
+
+    // the desktop no longer turns grabbed touches into a mouse, so one finger at a time plays the
+    // mouse for the buttons and sliders. Returns true when the event was used up that way.
+    fn touch_to_pointer(&mut self, event: &TouchInput, pos: Pos2, raw_input: &mut egui::RawInput) -> bool {
+        let modifiers = raw_input.modifiers;
+        let button = |pressed| egui::Event::PointerButton { pos, button: egui::PointerButton::Primary, pressed, modifiers };
+
+        match event.state {
+            TouchState::Began => {
+                let on_canvas = self.canvas_rect.is_some_and(|r| r.contains(pos)) && !self.playing_animation;
+                // thumbnails take raw touches for the long-press reorder
+                let on_frame_strip = self.frame_thumb_rects.iter().any(|r| r.contains(pos));
+                if self.pointer_touch.is_some() || on_canvas || on_frame_strip || event.tool == ContactTool::Eraser {
+                    return false;
+                }
+                self.pointer_touch = Some(PointerId::of_touch(event));
+                raw_input.events.push(egui::Event::PointerMoved(pos));
+                raw_input.events.push(button(true));
+                true
+            }
+            TouchState::Moved if self.pointer_touch == Some(PointerId::of_touch(event)) => {
+                raw_input.events.push(egui::Event::PointerMoved(pos));
+                true
+            }
+            // a cancelled contact is still released, egui would otherwise keep the button held.
+            // The palm filter's short-contact rule is about strokes, a quick tap is still a click here
+            TouchState::Ended | TouchState::Cancelled if self.pointer_touch == Some(PointerId::of_touch(event)) => {
+                self.pointer_touch = None;
+                raw_input.events.push(egui::Event::PointerMoved(pos));
+                raw_input.events.push(button(false));
+                raw_input.events.push(egui::Event::PointerGone);
+                true
+            }
+            _ => false,
+        }
+    }
+}
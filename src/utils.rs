@@ -1,6 +1,17 @@
-use eframe::egui::Pos2;
+use eframe::egui::{emath::RectTransform, Pos2, Rect, Vec2};
 use std::net::UdpSocket;
 
+// strokes are stored in this space, (0,0) is the top left of the canvas and (1,1) the bottom right
+pub const UNIT_RECT: Rect = Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0));
+
+pub fn canvas_to_screen(canvas_rect: Rect) -> RectTransform {
+    RectTransform::from_to(UNIT_RECT, canvas_rect)
+}
+
+pub fn screen_to_canvas(canvas_rect: Rect) -> RectTransform {
+    RectTransform::from_to(canvas_rect, UNIT_RECT)
+}
+
 pub fn distance_to_line_segment(p: Pos2, v: Pos2, w: Pos2) -> f32 {
     let l2 = v.distance_sq(w);
 
@@ -14,6 +25,76 @@ pub fn distance_to_line_segment(p: Pos2, v: Pos2, w: Pos2) -> f32 {
     p.distance(projection)
 }
 
+// segments of the round end caps on tapered outlines
+const CAP_SEGMENTS: usize = 8;
+
+/// Closed outline of a polyline whose width changes per point, with round caps at both ends.
+/// Meant to be filled with a non-zero rule so folds at sharp turns stay filled.
+pub fn taper_outline(points: &[Pos2], widths: &[f32]) -> Vec<Pos2> {
+    let mut samples: Vec<(Pos2, f32)> = Vec::with_capacity(points.len());
+    for (point, width) in points.iter().zip(widths) {
+        match samples.last_mut() {
+            Some(last) if last.0.distance(*point) < 0.01 => last.1 = last.1.max(*width),
+            _ => samples.push((*point, *width)),
+        }
+    }
+    if let [(center, width)] = samples[..] {
+        // a tap that never moved is just a dot
+        return (0..CAP_SEGMENTS * 2)
+            .map(|step| {
+                let angle = std::f32::consts::PI * step as f32 / CAP_SEGMENTS as f32;
+                center + Vec2::angled(angle) * (width / 2.0)
+            })
+            .collect();
+    }
+    if samples.len() < 2 {
+        return Vec::new();
+    }
+
+    let count = samples.len();
+    let directions: Vec<Vec2> = (0..count)
+        .map(|i| {
+            let before = samples[i.saturating_sub(1)].0;
+            let after = samples[(i + 1).min(count - 1)].0;
+            (after - before).normalized()
+        })
+        .collect();
+
+    let normal = |d: Vec2| Vec2::new(-d.y, d.x);
+    let mut outline = Vec::with_capacity(count * 2 + CAP_SEGMENTS * 2);
+
+    for ((point, width), d) in samples.iter().zip(&directions) {
+        outline.push(*point + normal(*d) * (width / 2.0));
+    }
+
+    let (end, end_width) = samples[count - 1];
+    let end_dir = directions[count - 1];
+    for step in 1..CAP_SEGMENTS {
+        let angle = std::f32::consts::PI * step as f32 / CAP_SEGMENTS as f32;
+        outline.push(end + (normal(end_dir) * angle.cos() + end_dir * angle.sin()) * (end_width / 2.0));
+    }
+
+    for ((point, width), d) in samples.iter().zip(&directions).rev() {
+        outline.push(*point - normal(*d) * (width / 2.0));
+    }
+
+    let (start, start_width) = samples[0];
+    let start_dir = directions[0];
+    for step in 1..CAP_SEGMENTS {
+        let angle = std::f32::consts::PI * step as f32 / CAP_SEGMENTS as f32;
+        outline.push(start - (normal(start_dir) * angle.cos() + start_dir * angle.sin()) * (start_width / 2.0));
+    }
+
+    outline
+}
+
+/// Corners of the trapezoid joining two round points of different widths.
+pub fn taper_segment(p1: Pos2, w1: f32, p2: Pos2, w2: f32) -> [Pos2; 4] {
+    let d = (p2 - p1).normalized();
+    let n = Vec2::new(-d.y, d.x);
+    [p1 + n * (w1 / 2.0), p2 + n * (w2 / 2.0), p2 - n * (w2 / 2.0), p1 - n * (w1 / 2.0)]
+}
+
 pub fn get_local_ip_address() -> Option<String> {
     match UdpSocket::bind("0.0.0.0:0") {
         Ok(socket) => {
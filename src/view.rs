@@ -0,0 +1,65 @@
+use eframe::egui::{self, emath::TSTransform, Pos2, Rect};
+use crate::app::PaintingApp;
+
+pub const MAX_ZOOM: f32 = 8.0;
+// how much one wheel notch (about 50 points of scroll) zooms
+const WHEEL_ZOOM_SPEED: f32 = 0.004;
+
+impl PaintingApp {
+    // the view maps the unzoomed canvas rect onto the screen, strokes themselves never change
+    pub fn view_canvas_rect(&self, canvas_rect: Rect) -> Rect {
+        self.view * canvas_rect
+    }
+
+    pub fn is_view_zoomed(&self) -> bool {
+        self.view != TSTransform::IDENTITY
+    }
+
+    pub fn reset_view(&mut self) {
+        self.view = TSTransform::IDENTITY;
+    }
+
+    // scales the view by `scale` around `from` and moves that point to `to`
+    pub fn zoom_view(&mut self, from: Pos2, to: Pos2, scale: f32, canvas_rect: Rect) {
+        let scale = scale.clamp(1.0 / MAX_ZOOM, MAX_ZOOM);
+        let zoom = (self.view.scaling * scale).clamp(1.0, MAX_ZOOM);
+        let scale = zoom / self.view.scaling;
+        self.view = TSTransform::from_translation(to.to_vec2())
+            * TSTransform::from_scaling(scale)
+            * TSTransform::from_translation(-from.to_vec2())
+            * self.view;
+        self.clamp_view(canvas_rect);
+    }
+
+    // the zoomed canvas always covers the canvas area, so there's no panning off into nothing
+    fn clamp_view(&mut self, canvas_rect: Rect) {
+        let view_rect = self.view * canvas_rect;
+        let mut shift = egui::Vec2::ZERO;
+        if view_rect.min.x > canvas_rect.min.x {
+            shift.x = canvas_rect.min.x - view_rect.min.x;
+        } else if view_rect.max.x < canvas_rect.max.x {
+            shift.x = canvas_rect.max.x - view_rect.max.x;
+        }
+        if view_rect.min.y > canvas_rect.min.y {
+            shift.y = canvas_rect.min.y - view_rect.min.y;
+        } else if view_rect.max.y < canvas_rect.max.y {
+            shift.y = canvas_rect.max.y - view_rect.max.y;
+        }
+        self.view = TSTransform::from_translation(shift) * self.view;
+    }
+
+    pub fn handle_view_wheel(&mut self, ctx: &egui::Context, canvas_rect: Rect) {
+        let Some(pointer) = ctx.input(|i| i.pointer.hover_pos()).filter(|p| canvas_rect.contains(*p)) else {
+            return;
+        };
+        // ctrl+wheel and trackpad pinches arrive as zoom, a plain wheel scrolls. egui also reports
+        // touchscreen pinches as zoom, those are already handled by the gesture tracker
+        let scale = ctx.input(|i| {
+            let zoom = if i.multi_touch().is_some() { 1.0 } else { i.zoom_delta() };
+            zoom * (i.smooth_scroll_delta.y * WHEEL_ZOOM_SPEED).exp()
+        });
+        if scale != 1.0 {
+            self.zoom_view(pointer, pointer, scale, canvas_rect);
+        }
+    }
+}
@@ -198,7 +198,7 @@ def main():
                 os.path.abspath(DRAWING_APP_PATH),
                 "--input", m['touchscreen']['event_node'],
                 "--x-offset", str(m['monitor_info']['x_offset']),
-                "--invert"
+                "--touch-rotate", "90"
             ]
             env = os.environ.copy()
             env["DISPLAY"] = os.environ.get("DISPLAY", ":0")